@@ -0,0 +1,166 @@
+use crate::model::{Board, Position, Transition};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, VecDeque};
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Search {
+    Bfs,
+    Dijkstra,
+    AStar
+}
+
+impl fmt::Display for Search {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Search::Bfs => "BFS",
+            Search::Dijkstra => "Dijkstra",
+            Search::AStar => "A*"
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(PartialEq)]
+struct Frontier {
+    priority: f64,
+    cost: f64,
+    cell: Position
+}
+
+impl Eq for Frontier {}
+
+impl Ord for Frontier {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Reversed so `BinaryHeap` pops the lowest priority first.
+        other.priority.total_cmp(&self.priority)
+    }
+}
+
+impl PartialOrd for Frontier {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+fn walk_back(parents: &[Vec<Option<Position>>], start: Position, finish: Position) -> Vec<Position> {
+    let mut path = vec![finish];
+    let mut cell = finish;
+    while cell != start {
+        cell = parents[cell.row][cell.col].unwrap();
+        path.push(cell);
+    }
+    path.reverse();
+    path
+}
+
+fn bfs(board: &Board) -> Option<Vec<Position>> {
+    let (rows, columns) = board.dimensions();
+    let mut parents: Vec<Vec<Option<Position>>> = vec![vec![None; columns]; rows];
+    let mut seen = vec![vec![false; columns]; rows];
+    let mut queue = VecDeque::from([board.start()]);
+    seen[board.start().row][board.start().col] = true;
+    while let Some(cell) = queue.pop_front() {
+        if cell == board.finish() {
+            return Some(walk_back(&parents, board.start(), board.finish()));
+        }
+        for next in board.neighbours(cell) {
+            if !seen[next.row][next.col] {
+                seen[next.row][next.col] = true;
+                parents[next.row][next.col] = Some(cell);
+                queue.push_back(next);
+            }
+        }
+    }
+    None
+}
+
+/// Dijkstra when `heuristic` is false, A* with the Manhattan distance otherwise.
+fn best_first(board: &Board, heuristic: bool) -> Option<Vec<Position>> {
+    let (rows, columns) = board.dimensions();
+    let estimate = |cell: Position| if heuristic { cell.manhattan(board.finish()) as f64 } else { 0.0 };
+    let mut parents: Vec<Vec<Option<Position>>> = vec![vec![None; columns]; rows];
+    let mut costs = vec![vec![f64::INFINITY; columns]; rows];
+    let mut heap = BinaryHeap::new();
+    costs[board.start().row][board.start().col] = 0.0;
+    heap.push(Frontier { priority: estimate(board.start()), cost: 0.0, cell: board.start() });
+    while let Some(Frontier { cost, cell, .. }) = heap.pop() {
+        if cell == board.finish() {
+            return Some(walk_back(&parents, board.start(), board.finish()));
+        }
+        if cost > costs[cell.row][cell.col] {
+            continue;
+        }
+        for next in board.neighbours(cell) {
+            let next_cost = cost + 1.0;
+            if next_cost < costs[next.row][next.col] {
+                costs[next.row][next.col] = next_cost;
+                parents[next.row][next.col] = Some(cell);
+                heap.push(Frontier { priority: next_cost + estimate(next), cost: next_cost, cell: next });
+            }
+        }
+    }
+    None
+}
+
+/// Shortest path from start to finish as a list of cells, both ends
+/// included, or `None` when the finish is unreachable. Fuel is not taken
+/// into account.
+pub fn shortest_path(board: &Board, search: Search) -> Option<Vec<Position>> {
+    match search {
+        Search::Bfs => bfs(board),
+        Search::Dijkstra => best_first(board, false),
+        Search::AStar => best_first(board, true)
+    }
+}
+
+/// How a greedy rollout compares with the shortest path.
+#[derive(Clone, Debug)]
+pub struct Optimality {
+    pub path_length: usize,
+    pub optimal_length: Option<usize>,
+    pub reached_finish: bool
+}
+
+impl Optimality {
+    pub fn ratio(&self) -> Option<f64> {
+        match self.optimal_length {
+            Some(optimal) if self.reached_finish && optimal > 0 => Some(self.path_length as f64 / optimal as f64),
+            _ => None
+        }
+    }
+
+    pub fn is_optimal(&self) -> bool {
+        self.reached_finish && self.optimal_length == Some(self.path_length)
+    }
+}
+
+impl fmt::Display for Optimality {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let optimal = match self.optimal_length {
+            Some(length) => length.to_string(),
+            None => String::from("unreachable")
+        };
+        write!(f, "Path length: {} | Optimal length: {}", self.path_length, optimal)?;
+        match self.ratio() {
+            Some(ratio) => write!(f, " | Ratio: {:.2}", ratio)?,
+            None => write!(f, " | Ratio: n/a")?
+        }
+        if self.is_optimal() {
+            write!(f, " | Optimal")
+        } else if self.reached_finish {
+            write!(f, " | Suboptimal")
+        } else {
+            write!(f, " | Did not reach the finish")
+        }
+    }
+}
+
+/// Compares a rollout from `Board::trajectory` with the shortest path found by `search`.
+pub fn optimality(board: &Board, trajectory: &[Transition], search: Search) -> Optimality {
+    Optimality {
+        path_length: trajectory.len(),
+        optimal_length: shortest_path(board, search).map(|path| path.len() - 1),
+        reached_finish: trajectory.last().is_some_and(|step| step.1 == board.finish())
+    }
+}
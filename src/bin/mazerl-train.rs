@@ -0,0 +1,272 @@
+use mazerl::analysis;
+use mazerl::checkpoint::Checkpoint;
+use mazerl::demonstration::{Demonstrations, OfflineUpdate};
+use mazerl::export::{self, Evaluation, RunMetadata};
+use mazerl::maze_file::{MazeFile, MazeFileError};
+use mazerl::metrics::TrainingHistory;
+use mazerl::movingai;
+use mazerl::{Board, Position};
+use std::env;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::str::FromStr;
+
+const USAGE: &str = "\
+Usage: mazerl-train <maze-file> [options]
+       mazerl-train --resume <checkpoint> [options]
+
+A Moving AI .map file needs --scenarios to supply the start and goal. A
+resumed run keeps the maze, rewards, fuel budget and exploration stream of
+its checkpoint, so --fuel, --fuel-penalty, --scenarios, --scenario and
+--seed cannot be combined with --resume.
+
+Options:
+  --episodes <n>          Training episodes (default 10000)
+  --trajectory-limit <n>  Step limit per episode (default 1000)
+  --discount-rate <x>     Discount rate (default 1.0)
+  --learning-rate <x>     Learning rate (default 0.1)
+  --epsilon <x>           Exploration rate (default 0.9)
+  --fuel <n>              Fuel budget per episode, off by default
+  --fuel-penalty <x>      Reward for running out of fuel (default -100)
+  --eval-every <n>        Episodes between greedy evaluations (default 100)
+  --scenarios <file>      Moving AI .scen file for a .map maze
+  --scenario <n>          Scenario index in the .scen file (default 0)
+  --seed <n>              Seed for exploration, random by default; a
+                          resumed run reports its checkpoint's seed
+  --resume <file>         Continue training from a checkpoint
+  --checkpoint <file>     Save a checkpoint after every evaluation
+  --demonstrations <file> Learn from recorded episodes before training
+  --demo-update <kind>    monte-carlo, q-learning or cloning (default
+                          monte-carlo)
+  --out <dir>             Output directory (default .)
+
+The output directory receives learning_curve.csv, q_table.csv, path.csv,
+and the per-episode history with run metadata in episodes.csv,
+evaluation.csv and run.jsonl.";
+
+struct Options {
+    maze: Option<PathBuf>,
+    episodes: u32,
+    trajectory_limit: u32,
+    discount_rate: f64,
+    learning_rate: f64,
+    epsilon: f64,
+    fuel: Option<u32>,
+    fuel_penalty: f64,
+    eval_every: u32,
+    scenarios: Option<PathBuf>,
+    scenario: usize,
+    seed: Option<u64>,
+    resume: Option<PathBuf>,
+    checkpoint: Option<PathBuf>,
+    demonstrations: Option<PathBuf>,
+    demo_update: OfflineUpdate,
+    out: PathBuf
+}
+
+fn value<T: FromStr>(flag: &str, raw: Option<String>) -> Result<T, String> {
+    let raw = raw.ok_or_else(|| format!("{} needs a value", flag))?;
+    raw.parse().map_err(|_| format!("invalid value {:?} for {}", raw, flag))
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut args = env::args().skip(1);
+    // Flags that shape the maze or its exploration, which a checkpoint fixes.
+    let mut fixed_by_checkpoint: Vec<String> = Vec::new();
+    let mut options = Options {
+        maze: None,
+        episodes: 10000,
+        trajectory_limit: 1000,
+        discount_rate: 1.0,
+        learning_rate: 0.1,
+        epsilon: 0.9,
+        fuel: None,
+        fuel_penalty: -100.0,
+        eval_every: 100,
+        scenarios: None,
+        scenario: 0,
+        seed: None,
+        resume: None,
+        checkpoint: None,
+        demonstrations: None,
+        demo_update: OfflineUpdate::MonteCarlo,
+        out: PathBuf::from(".")
+    };
+    while let Some(arg) = args.next() {
+        if matches!(arg.as_str(), "--fuel" | "--fuel-penalty" | "--scenarios" | "--scenario" | "--seed") {
+            fixed_by_checkpoint.push(arg.clone());
+        }
+        match arg.as_str() {
+            "--episodes" => options.episodes = value(&arg, args.next())?,
+            "--trajectory-limit" => options.trajectory_limit = value(&arg, args.next())?,
+            "--discount-rate" => options.discount_rate = value(&arg, args.next())?,
+            "--learning-rate" => options.learning_rate = value(&arg, args.next())?,
+            "--epsilon" => options.epsilon = value(&arg, args.next())?,
+            "--fuel" => options.fuel = Some(value(&arg, args.next())?),
+            "--fuel-penalty" => options.fuel_penalty = value(&arg, args.next())?,
+            "--eval-every" => options.eval_every = value::<u32>(&arg, args.next())?.max(1),
+            "--scenarios" => options.scenarios = Some(value(&arg, args.next())?),
+            "--scenario" => options.scenario = value(&arg, args.next())?,
+            "--seed" => options.seed = Some(value(&arg, args.next())?),
+            "--resume" => options.resume = Some(value(&arg, args.next())?),
+            "--checkpoint" => options.checkpoint = Some(value(&arg, args.next())?),
+            "--demonstrations" => options.demonstrations = Some(value(&arg, args.next())?),
+            "--demo-update" => options.demo_update = value(&arg, args.next())?,
+            "--out" => options.out = value(&arg, args.next())?,
+            "-h" | "--help" => return Err(String::new()),
+            flag if flag.starts_with("--") => return Err(format!("unknown option {}", flag)),
+            _ if options.maze.is_none() => options.maze = Some(PathBuf::from(arg)),
+            _ => return Err(format!("unexpected argument {}", arg))
+        }
+    }
+    if options.maze.is_some() == options.resume.is_some() {
+        return Err("give either a maze file or --resume".to_string());
+    }
+    if options.resume.is_some() && !fixed_by_checkpoint.is_empty() {
+        return Err(format!(
+            "{} cannot be combined with --resume, which restores the checkpoint's maze, fuel budget and exploration stream",
+            fixed_by_checkpoint.join(", ")
+        ));
+    }
+    Ok(options)
+}
+
+fn write_q_table(board: &Board, path: PathBuf) -> std::io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    writeln!(out, "row,col,fuel,action,value,visits")?;
+    let (rows, columns) = board.dimensions();
+    let levels: Vec<Option<u32>> = match board.fuel_capacity() {
+        Some(capacity) => (1..=capacity).map(Some).collect(),
+        None => vec![None]
+    };
+    for row in 0..rows {
+        for col in 0..columns {
+            let cell = Position::new(row, col);
+            for &level in levels.iter() {
+                let state = match level {
+                    Some(fuel) => board.state_with_fuel(cell, fuel).unwrap(),
+                    None => board.state(cell)
+                };
+                let fuel = level.map_or(String::new(), |fuel| fuel.to_string());
+                for ((action, value), visits) in state.actions().iter().zip(state.action_values()).zip(state.visits()) {
+                    writeln!(out, "{},{},{},{:?},{},{}", row, col, fuel, action, value, visits)?;
+                }
+            }
+        }
+    }
+    out.flush()
+}
+
+fn located(path: &Path) -> impl Fn(MazeFileError) -> String + '_ {
+    move |e| format!("{}: {}", path.display(), e)
+}
+
+fn load_maze(path: &Path, options: &Options) -> Result<MazeFile, String> {
+    if path.extension().is_none_or(|extension| extension != "map") {
+        return MazeFile::load(path).map_err(located(path));
+    }
+    let scenarios_path = options.scenarios.as_ref().ok_or("a .map maze needs --scenarios")?;
+    let cells = movingai::load_map(path).map_err(located(path))?;
+    let scenarios = movingai::load_scenarios(scenarios_path).map_err(located(scenarios_path))?;
+    let scenario = scenarios
+        .get(options.scenario)
+        .ok_or_else(|| format!("{} has only {} scenario(s)", scenarios_path.display(), scenarios.len()))?;
+    Ok(scenario.maze(cells))
+}
+
+fn load_board(options: &Options) -> Result<Board, String> {
+    if let Some(path) = &options.resume {
+        let checkpoint = Checkpoint::load(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        return Board::from_checkpoint(checkpoint).map_err(|e| format!("{}: {}", path.display(), e));
+    }
+    let path = options.maze.as_ref().expect("parse_args requires a maze without --resume");
+    let maze = load_maze(path, options)?;
+    let mut board = maze.to_board().map_err(|e| e.to_string())?;
+    let validation = board.validate();
+    if !validation.is_solvable() {
+        return Err(validation.to_string());
+    }
+    if let Some(capacity) = options.fuel {
+        board = board.with_fuel(capacity, &maze.chargers(), options.fuel_penalty).map_err(|e| e.to_string())?;
+    }
+    Ok(board)
+}
+
+fn run(options: Options) -> Result<(), String> {
+    let mut board = load_board(&options)?;
+    if let Some(seed) = options.seed {
+        board = board.with_seed(seed);
+    }
+    if let Some(path) = &options.demonstrations {
+        let located = |e| format!("{}: {}", path.display(), e);
+        let demonstrations = Demonstrations::load(path).map_err(located)?;
+        board
+            .learn_from_demonstrations(&demonstrations.episodes, options.demo_update, options.discount_rate, options.learning_rate)
+            .map_err(located)?;
+        eprintln!("Learned from {} demonstration(s) with {}", demonstrations.episodes.len(), options.demo_update);
+    }
+    let first_episode = board.episodes();
+    fs::create_dir_all(&options.out).map_err(|e| e.to_string())?;
+
+    let io_error = |e: std::io::Error| e.to_string();
+    let mut curve = BufWriter::new(File::create(options.out.join("learning_curve.csv")).map_err(io_error)?);
+    writeln!(curve, "episode,greedy_length,reached_finish").map_err(io_error)?;
+    let mut history = TrainingHistory::new();
+    let mut trained = 0;
+    while trained < options.episodes {
+        let batch = options.eval_every.min(options.episodes - trained);
+        history.extend(board.train(batch, options.trajectory_limit, options.discount_rate, options.learning_rate, options.epsilon));
+        trained += batch;
+        let greedy = board.trajectory(options.trajectory_limit, 0.0);
+        let reached = greedy.last().is_some_and(|step| step.1 == board.finish());
+        writeln!(curve, "{},{},{}", board.episodes(), greedy.len(), reached).map_err(io_error)?;
+        if let Some(path) = &options.checkpoint {
+            board.checkpoint().save(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        }
+        eprint!("\rEpisode {}/{}", board.episodes(), first_episode + u64::from(options.episodes));
+    }
+    eprintln!();
+    curve.flush().map_err(io_error)?;
+
+    write_q_table(&board, options.out.join("q_table.csv")).map_err(io_error)?;
+
+    let path = board.trajectory(options.trajectory_limit, 0.0);
+    let mut out = BufWriter::new(File::create(options.out.join("path.csv")).map_err(io_error)?);
+    writeln!(out, "step,from_row,from_col,to_row,to_col").map_err(io_error)?;
+    for (i, (from, to)) in path.iter().enumerate() {
+        writeln!(out, "{},{},{},{},{}", i, from.row, from.col, to.row, to.col).map_err(io_error)?;
+    }
+    out.flush().map_err(io_error)?;
+
+    let optimality = analysis::optimality(&board, &path, analysis::Search::Bfs);
+    let run = RunMetadata::new(&board, options.trajectory_limit, options.discount_rate, options.learning_rate, options.epsilon);
+    export::write_run(&options.out, &run, &history, &Evaluation::new(&optimality, analysis::Search::Bfs)).map_err(io_error)?;
+
+    println!("{}", history);
+    println!("{}", optimality);
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let options = match parse_args() {
+        Ok(options) => options,
+        Err(message) if message.is_empty() => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        },
+        Err(message) => {
+            eprintln!("error: {}", message);
+            eprintln!("{}", USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+    match run(options) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("error: {}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
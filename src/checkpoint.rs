@@ -0,0 +1,96 @@
+use crate::model::{MazeError, Position, Rewards};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+pub const CHECKPOINT_VERSION: u32 = 1;
+
+#[derive(Debug)]
+pub enum CheckpointError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    UnsupportedVersion(u32),
+    Maze(MazeError),
+    Shape
+}
+
+impl fmt::Display for CheckpointError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckpointError::Io(e) => write!(f, "{}", e),
+            CheckpointError::Json(e) => write!(f, "Invalid checkpoint: {}", e),
+            CheckpointError::UnsupportedVersion(version) => write!(f, "Unsupported checkpoint version {}.", version),
+            CheckpointError::Maze(e) => write!(f, "Checkpoint maze is invalid: {}", e),
+            CheckpointError::Shape => write!(f, "Checkpoint tables do not match its maze.")
+        }
+    }
+}
+
+impl std::error::Error for CheckpointError {}
+
+impl From<std::io::Error> for CheckpointError {
+    fn from(e: std::io::Error) -> Self {
+        CheckpointError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for CheckpointError {
+    fn from(e: serde_json::Error) -> Self {
+        CheckpointError::Json(e)
+    }
+}
+
+impl From<MazeError> for CheckpointError {
+    fn from(e: MazeError) -> Self {
+        CheckpointError::Maze(e)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FuelBudget {
+    pub capacity: u32,
+    pub chargers: Vec<Position>,
+    pub out_of_fuel_penalty: f64
+}
+
+/// Position of the ChaCha8 exploration stream, enough to continue it exactly.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RngState {
+    pub seed: [u8; 32],
+    pub stream: u64,
+    pub word_pos: u128
+}
+
+/// Everything needed to resume training, as written by `Board::checkpoint`.
+/// `action_values` and `visits` are indexed `[row][col][fuel level - 1][action]`,
+/// following the action order of `State::actions`. `seed` is the seed the run
+/// started from, if any, for reporting; `rng` is what actually resumes it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub version: u32,
+    pub episodes: u64,
+    pub rows: usize,
+    pub columns: usize,
+    pub start: Position,
+    pub finish: Position,
+    pub blocked: Vec<Position>,
+    pub rewards: Rewards,
+    pub fuel: Option<FuelBudget>,
+    pub action_values: Vec<Vec<Vec<Vec<f64>>>>,
+    pub visits: Vec<Vec<Vec<Vec<u64>>>>,
+    pub rng: RngState,
+    #[serde(default)]
+    pub seed: Option<u64>
+}
+
+impl Checkpoint {
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), CheckpointError> {
+        fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, CheckpointError> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+}
@@ -0,0 +1,118 @@
+use crate::model::EpisodeStep;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+pub const DEMONSTRATION_VERSION: u32 = 1;
+
+#[derive(Debug)]
+pub enum DemonstrationError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    UnsupportedVersion(u32),
+    /// The step at these 0-based indices starts from a cell, fuel level or
+    /// action the maze does not have, does not end where its action leads, or
+    /// does not start where the previous step ended. Rewards are not checked.
+    Mismatch { episode: usize, step: usize }
+}
+
+impl fmt::Display for DemonstrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DemonstrationError::Io(e) => write!(f, "{}", e),
+            DemonstrationError::Json(e) => write!(f, "Invalid demonstrations: {}", e),
+            DemonstrationError::UnsupportedVersion(version) => write!(f, "Unsupported demonstrations version {}.", version),
+            DemonstrationError::Mismatch { episode, step } => write!(
+                f,
+                "Step {} of demonstration {} does not fit this maze or does not follow the step before it (rewards are taken from the file as they are).",
+                step + 1,
+                episode + 1
+            )
+        }
+    }
+}
+
+impl std::error::Error for DemonstrationError {}
+
+impl From<std::io::Error> for DemonstrationError {
+    fn from(e: std::io::Error) -> Self {
+        DemonstrationError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for DemonstrationError {
+    fn from(e: serde_json::Error) -> Self {
+        DemonstrationError::Json(e)
+    }
+}
+
+/// How `Board::learn_from_demonstration` turns a recorded episode into
+/// action values.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OfflineUpdate {
+    /// The every-visit Monte Carlo update used in training.
+    MonteCarlo,
+    /// One-step Q-learning over the moves, replayed from last to first so
+    /// the final reward reaches the start in a single pass.
+    QLearning,
+    /// Raises each demonstrated action just above its alternatives, so the
+    /// greedy policy follows the demonstration until training overrides it.
+    BehaviorCloning
+}
+
+impl OfflineUpdate {
+    pub const ALL: [OfflineUpdate; 3] = [OfflineUpdate::MonteCarlo, OfflineUpdate::QLearning, OfflineUpdate::BehaviorCloning];
+}
+
+impl fmt::Display for OfflineUpdate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            OfflineUpdate::MonteCarlo => "Monte Carlo",
+            OfflineUpdate::QLearning => "Q-Learning Replay",
+            OfflineUpdate::BehaviorCloning => "Behavior Cloning"
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for OfflineUpdate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "monte-carlo" => Ok(OfflineUpdate::MonteCarlo),
+            "q-learning" => Ok(OfflineUpdate::QLearning),
+            "cloning" => Ok(OfflineUpdate::BehaviorCloning),
+            _ => Err(format!("unknown update {:?}, expected monte-carlo, q-learning or cloning", s))
+        }
+    }
+}
+
+/// Episodes recorded outside training, such as by manual play, kept as
+/// offline experience for an agent.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Demonstrations {
+    pub version: u32,
+    pub episodes: Vec<Vec<EpisodeStep>>
+}
+
+impl Demonstrations {
+    pub fn new(episodes: Vec<Vec<EpisodeStep>>) -> Self {
+        Self { version: DEMONSTRATION_VERSION, episodes }
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), DemonstrationError> {
+        fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, DemonstrationError> {
+        let demonstrations: Self = serde_json::from_str(&fs::read_to_string(path)?)?;
+        if demonstrations.version != DEMONSTRATION_VERSION {
+            return Err(DemonstrationError::UnsupportedVersion(demonstrations.version));
+        }
+        Ok(demonstrations)
+    }
+}
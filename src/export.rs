@@ -0,0 +1,166 @@
+use crate::analysis::{Optimality, Search};
+use crate::metrics::{EpisodeMetrics, TrainingHistory};
+use crate::model::{Board, Rewards, ALGORITHM};
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// Describes a training run so exported histories can be told apart.
+#[derive(Clone, Debug, Serialize)]
+pub struct RunMetadata {
+    pub maze_hash: String,
+    pub algorithm: String,
+    pub rows: usize,
+    pub columns: usize,
+    pub episodes: u64,
+    pub trajectory_limit: u32,
+    pub discount_rate: f64,
+    pub learning_rate: f64,
+    pub epsilon: f64,
+    pub seed: Option<u64>,
+    pub rewards: Rewards,
+    pub fuel_capacity: Option<u32>
+}
+
+impl RunMetadata {
+    /// Metadata for `board` after training with the given hyperparameters.
+    /// The seed is `Board::seed`, which is None when exploration was seeded
+    /// from the OS.
+    pub fn new(board: &Board, trajectory_limit: u32, discount_rate: f64, learning_rate: f64, epsilon: f64) -> Self {
+        let (rows, columns) = board.dimensions();
+        Self {
+            maze_hash: format!("{:016x}", board.maze_hash()),
+            algorithm: ALGORITHM.to_string(),
+            rows,
+            columns,
+            episodes: board.episodes(),
+            trajectory_limit,
+            discount_rate,
+            learning_rate,
+            epsilon,
+            seed: board.seed(),
+            rewards: board.rewards(),
+            fuel_capacity: board.fuel_capacity()
+        }
+    }
+
+    fn fields(&self) -> Vec<(&'static str, String)> {
+        let optional = |value: Option<String>| value.unwrap_or_default();
+        vec![
+            ("maze_hash", self.maze_hash.clone()),
+            ("algorithm", self.algorithm.clone()),
+            ("rows", self.rows.to_string()),
+            ("columns", self.columns.to_string()),
+            ("episodes", self.episodes.to_string()),
+            ("trajectory_limit", self.trajectory_limit.to_string()),
+            ("discount_rate", self.discount_rate.to_string()),
+            ("learning_rate", self.learning_rate.to_string()),
+            ("epsilon", self.epsilon.to_string()),
+            ("seed", optional(self.seed.map(|seed| seed.to_string()))),
+            ("step_reward", self.rewards.step.to_string()),
+            ("finish_reward", self.rewards.finish.to_string()),
+            ("fuel_capacity", optional(self.fuel_capacity.map(|fuel| fuel.to_string())))
+        ]
+    }
+}
+
+/// A greedy rollout scored against the shortest path.
+#[derive(Clone, Debug, Serialize)]
+pub struct Evaluation {
+    pub oracle: String,
+    pub path_length: usize,
+    pub optimal_length: Option<usize>,
+    pub ratio: Option<f64>,
+    pub reached_finish: bool,
+    pub optimal: bool
+}
+
+impl Evaluation {
+    pub fn new(optimality: &Optimality, oracle: Search) -> Self {
+        Self {
+            oracle: oracle.to_string(),
+            path_length: optimality.path_length,
+            optimal_length: optimality.optimal_length,
+            ratio: optimality.ratio(),
+            reached_finish: optimality.reached_finish,
+            optimal: optimality.is_optimal()
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum Record<'a> {
+    Run(&'a RunMetadata),
+    Episode(&'a EpisodeMetrics),
+    Evaluation(&'a Evaluation)
+}
+
+/// Per-episode metrics as CSV, preceded by the run metadata as `# key: value`
+/// comment lines.
+pub fn write_episodes_csv(path: impl AsRef<Path>, run: &RunMetadata, history: &TrainingHistory) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    for (key, value) in run.fields() {
+        writeln!(out, "{}", format!("# {}: {}", key, value).trim_end())?;
+    }
+    writeln!(out, "episode,steps,total_reward,discounted_return,reached_finish,hit_limit,max_q_change,epsilon")?;
+    for e in history.episodes() {
+        writeln!(
+            out,
+            "{},{},{},{},{},{},{},{}",
+            e.episode, e.steps, e.total_reward, e.discounted_return, e.reached_finish, e.hit_limit, e.max_q_change, e.epsilon
+        )?;
+    }
+    out.flush()
+}
+
+/// One row with the run metadata followed by the evaluation, so files from
+/// many runs can be concatenated.
+pub fn write_evaluation_csv(path: impl AsRef<Path>, run: &RunMetadata, evaluation: &Evaluation) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    let fields = run.fields();
+    let optional = |value: Option<String>| value.unwrap_or_default();
+    let keys: Vec<&str> = fields.iter().map(|(key, _)| *key).collect();
+    let values: Vec<&str> = fields.iter().map(|(_, value)| value.as_str()).collect();
+    writeln!(out, "{},oracle,path_length,optimal_length,ratio,reached_finish,optimal", keys.join(","))?;
+    writeln!(
+        out,
+        "{},{},{},{},{},{},{}",
+        values.join(","),
+        evaluation.oracle,
+        evaluation.path_length,
+        optional(evaluation.optimal_length.map(|length| length.to_string())),
+        optional(evaluation.ratio.map(|ratio| ratio.to_string())),
+        evaluation.reached_finish,
+        evaluation.optimal
+    )?;
+    out.flush()
+}
+
+/// JSON Lines with a `run` record, one `episode` record per episode and, if
+/// given, a final `evaluation` record. Each record carries a `type` field.
+pub fn write_jsonl(path: impl AsRef<Path>, run: &RunMetadata, history: &TrainingHistory, evaluation: Option<&Evaluation>) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    let mut record = |record: Record| -> io::Result<()> {
+        serde_json::to_writer(&mut out, &record)?;
+        writeln!(out)
+    };
+    record(Record::Run(run))?;
+    for episode in history.episodes() {
+        record(Record::Episode(episode))?;
+    }
+    if let Some(evaluation) = evaluation {
+        record(Record::Evaluation(evaluation))?;
+    }
+    out.flush()
+}
+
+/// Writes `episodes.csv`, `evaluation.csv` and `run.jsonl` into `dir`.
+pub fn write_run(dir: impl AsRef<Path>, run: &RunMetadata, history: &TrainingHistory, evaluation: &Evaluation) -> io::Result<()> {
+    let dir = dir.as_ref();
+    fs::create_dir_all(dir)?;
+    write_episodes_csv(dir.join("episodes.csv"), run, history)?;
+    write_evaluation_csv(dir.join("evaluation.csv"), run, evaluation)?;
+    write_jsonl(dir.join("run.jsonl"), run, history, Some(evaluation))
+}
@@ -0,0 +1,413 @@
+use crate::model::Position;
+use rand::seq::{IndexedRandom, SliceRandom};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::collections::VecDeque;
+use std::fmt;
+
+const NEIGHBOURS: [(isize, isize); 4] = [(-1, 0), (0, 1), (1, 0), (0, -1)];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Generator {
+    Backtracker,
+    Prim,
+    Kruskal,
+    Wilson,
+    RecursiveDivision,
+    Caves
+}
+
+impl Generator {
+    pub const ALL: [Generator; 6] = [
+        Generator::Backtracker,
+        Generator::Prim,
+        Generator::Kruskal,
+        Generator::Wilson,
+        Generator::RecursiveDivision,
+        Generator::Caves
+    ];
+}
+
+impl fmt::Display for Generator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Generator::Backtracker => "Recursive Backtracker",
+            Generator::Prim => "Randomized Prim's",
+            Generator::Kruskal => "Randomized Kruskal's",
+            Generator::Wilson => "Wilson's",
+            Generator::RecursiveDivision => "Recursive Division",
+            Generator::Caves => "Cellular Automata Caves"
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A generated maze. `cells[i][j]` is `true` for blocked cells.
+pub struct Maze {
+    pub cells: Vec<Vec<bool>>,
+    pub start: Position,
+    pub finish: Position
+}
+
+/// Generates a `rows` x `columns` maze. The spanning-tree generators work on a
+/// lattice of rooms at even coordinates separated by one-cell walls, and
+/// `loop_ratio` is the fraction of their dead ends that get knocked through
+/// to make a braided maze. Cave generation ignores `loop_ratio`. Generation
+/// uses ChaCha8, so a seed rebuilds the same maze on any platform.
+pub fn generate(generator: Generator, rows: usize, columns: usize, seed: u64, loop_ratio: f64) -> Maze {
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let mut lattice = Lattice::new(rows, columns);
+    let mut cells = match generator {
+        Generator::Backtracker => {
+            lattice.backtracker(&mut rng);
+            lattice.braid(&mut rng, loop_ratio);
+            lattice.cells
+        },
+        Generator::Prim => {
+            lattice.prim(&mut rng);
+            lattice.braid(&mut rng, loop_ratio);
+            lattice.cells
+        },
+        Generator::Kruskal => {
+            lattice.kruskal(&mut rng);
+            lattice.braid(&mut rng, loop_ratio);
+            lattice.cells
+        },
+        Generator::Wilson => {
+            lattice.wilson(&mut rng);
+            lattice.braid(&mut rng, loop_ratio);
+            lattice.cells
+        },
+        Generator::RecursiveDivision => {
+            lattice.recursive_division(&mut rng);
+            lattice.braid(&mut rng, loop_ratio);
+            lattice.cells
+        },
+        Generator::Caves => caves(rows, columns, &mut rng)
+    };
+    let (start, finish) = endpoints(&mut cells);
+    Maze {
+        cells,
+        start: start.into(),
+        finish: finish.into()
+    }
+}
+
+fn step(cell: (usize, usize), direction: (isize, isize), rows: usize, columns: usize) -> Option<(usize, usize)> {
+    let i = cell.0.checked_add_signed(direction.0)?;
+    let j = cell.1.checked_add_signed(direction.1)?;
+    if i < rows && j < columns { Some((i, j)) } else { None }
+}
+
+/// Breadth-first distances over open cells from `from`, `usize::MAX` where unreachable.
+fn distances(cells: &[Vec<bool>], from: (usize, usize)) -> Vec<Vec<usize>> {
+    let rows = cells.len();
+    let columns = cells[0].len();
+    let mut dist = vec![vec![usize::MAX; columns]; rows];
+    let mut queue = VecDeque::new();
+    dist[from.0][from.1] = 0;
+    queue.push_back(from);
+    while let Some(cell) = queue.pop_front() {
+        for direction in NEIGHBOURS {
+            if let Some(next) = step(cell, direction, rows, columns)
+                && !cells[next.0][next.1]
+                && dist[next.0][next.1] == usize::MAX {
+                dist[next.0][next.1] = dist[cell.0][cell.1] + 1;
+                queue.push_back(next);
+            }
+        }
+    }
+    dist
+}
+
+fn farthest(dist: &[Vec<usize>]) -> (usize, usize) {
+    let mut best = (0, 0);
+    let mut best_dist = 0;
+    for (i, row) in dist.iter().enumerate() {
+        for (j, &d) in row.iter().enumerate() {
+            if d != usize::MAX && d >= best_dist {
+                best = (i, j);
+                best_dist = d;
+            }
+        }
+    }
+    best
+}
+
+/// Picks start and finish as the ends of a double breadth-first sweep, which
+/// lands on (or close to) the longest shortest path in the maze. When only one
+/// cell is open, as in a 2x2 lattice, a neighbour is carved for the finish so
+/// the two never coincide on grids of two or more cells.
+fn endpoints(cells: &mut [Vec<bool>]) -> ((usize, usize), (usize, usize)) {
+    let mut first = (0, 0);
+    'search: for (i, row) in cells.iter().enumerate() {
+        for (j, &blocked) in row.iter().enumerate() {
+            if !blocked {
+                first = (i, j);
+                break 'search;
+            }
+        }
+    }
+    cells[first.0][first.1] = false;
+    let start = farthest(&distances(cells, first));
+    let mut finish = farthest(&distances(cells, start));
+    if finish == start
+        && let Some(next) = NEIGHBOURS.iter().find_map(|&direction| step(start, direction, cells.len(), cells[0].len())) {
+        cells[next.0][next.1] = false;
+        finish = next;
+    }
+    (start, finish)
+}
+
+struct Lattice {
+    cells: Vec<Vec<bool>>,
+    rooms: (usize, usize)
+}
+
+impl Lattice {
+    fn new(rows: usize, columns: usize) -> Self {
+        let mut cells = vec![vec![true; columns]; rows];
+        let rooms = (rows.div_ceil(2), columns.div_ceil(2));
+        for r in 0..rooms.0 {
+            for c in 0..rooms.1 {
+                cells[2 * r][2 * c] = false;
+            }
+        }
+        Self { cells, rooms }
+    }
+
+    fn neighbours(&self, room: (usize, usize)) -> Vec<(usize, usize)> {
+        NEIGHBOURS
+            .iter()
+            .filter_map(|&direction| step(room, direction, self.rooms.0, self.rooms.1))
+            .collect()
+    }
+
+    fn is_open(&self, a: (usize, usize), b: (usize, usize)) -> bool {
+        !self.cells[a.0 + b.0][a.1 + b.1]
+    }
+
+    fn set_open(&mut self, a: (usize, usize), b: (usize, usize), open: bool) {
+        self.cells[a.0 + b.0][a.1 + b.1] = !open;
+    }
+
+    fn edges(&self) -> Vec<((usize, usize), (usize, usize))> {
+        let mut edges = Vec::new();
+        for r in 0..self.rooms.0 {
+            for c in 0..self.rooms.1 {
+                if r + 1 < self.rooms.0 {
+                    edges.push(((r, c), (r + 1, c)));
+                }
+                if c + 1 < self.rooms.1 {
+                    edges.push(((r, c), (r, c + 1)));
+                }
+            }
+        }
+        edges
+    }
+
+    fn backtracker(&mut self, rng: &mut ChaCha8Rng) {
+        let mut visited = vec![vec![false; self.rooms.1]; self.rooms.0];
+        let mut stack = vec![(0, 0)];
+        visited[0][0] = true;
+        while let Some(&room) = stack.last() {
+            let unvisited: Vec<(usize, usize)> = self.neighbours(room)
+                .into_iter()
+                .filter(|n| !visited[n.0][n.1])
+                .collect();
+            if let Some(&next) = unvisited.choose(rng) {
+                self.set_open(room, next, true);
+                visited[next.0][next.1] = true;
+                stack.push(next);
+            } else {
+                stack.pop();
+            }
+        }
+    }
+
+    fn prim(&mut self, rng: &mut ChaCha8Rng) {
+        let mut visited = vec![vec![false; self.rooms.1]; self.rooms.0];
+        let mut frontier: Vec<((usize, usize), (usize, usize))> = Vec::new();
+        let first = (rng.random_range(0..self.rooms.0), rng.random_range(0..self.rooms.1));
+        visited[first.0][first.1] = true;
+        frontier.extend(self.neighbours(first).into_iter().map(|n| (first, n)));
+        while !frontier.is_empty() {
+            let (from, to) = frontier.swap_remove(rng.random_range(0..frontier.len()));
+            if visited[to.0][to.1] {
+                continue;
+            }
+            self.set_open(from, to, true);
+            visited[to.0][to.1] = true;
+            frontier.extend(
+                self.neighbours(to)
+                    .into_iter()
+                    .filter(|n| !visited[n.0][n.1])
+                    .map(|n| (to, n))
+            );
+        }
+    }
+
+    fn kruskal(&mut self, rng: &mut ChaCha8Rng) {
+        let mut parent: Vec<usize> = (0..self.rooms.0 * self.rooms.1).collect();
+        fn root(parent: &mut [usize], mut x: usize) -> usize {
+            while parent[x] != x {
+                parent[x] = parent[parent[x]];
+                x = parent[x];
+            }
+            x
+        }
+        let mut edges = self.edges();
+        edges.shuffle(rng);
+        for (a, b) in edges {
+            let ra = root(&mut parent, a.0 * self.rooms.1 + a.1);
+            let rb = root(&mut parent, b.0 * self.rooms.1 + b.1);
+            if ra != rb {
+                parent[ra] = rb;
+                self.set_open(a, b, true);
+            }
+        }
+    }
+
+    fn wilson(&mut self, rng: &mut ChaCha8Rng) {
+        let mut in_tree = vec![vec![false; self.rooms.1]; self.rooms.0];
+        in_tree[rng.random_range(0..self.rooms.0)][rng.random_range(0..self.rooms.1)] = true;
+        for r in 0..self.rooms.0 {
+            for c in 0..self.rooms.1 {
+                if in_tree[r][c] {
+                    continue;
+                }
+                // Loop-erased random walk from (r, c) until it hits the tree.
+                let mut path = vec![(r, c)];
+                while let Some(&room) = path.last() {
+                    if in_tree[room.0][room.1] {
+                        break;
+                    }
+                    let next = *self.neighbours(room).choose(rng).unwrap();
+                    if let Some(pos) = path.iter().position(|&p| p == next) {
+                        path.truncate(pos + 1);
+                    } else {
+                        path.push(next);
+                    }
+                }
+                for pair in path.windows(2) {
+                    self.set_open(pair[0], pair[1], true);
+                    in_tree[pair[0].0][pair[0].1] = true;
+                }
+            }
+        }
+    }
+
+    fn recursive_division(&mut self, rng: &mut ChaCha8Rng) {
+        for (a, b) in self.edges() {
+            self.set_open(a, b, true);
+        }
+        self.divide(rng, (0, self.rooms.0), (0, self.rooms.1));
+    }
+
+    fn divide(&mut self, rng: &mut ChaCha8Rng, rows: (usize, usize), columns: (usize, usize)) {
+        let height = rows.1 - rows.0;
+        let width = columns.1 - columns.0;
+        if height < 2 && width < 2 {
+            return;
+        }
+        if height >= width {
+            let split = rng.random_range(rows.0 + 1..rows.1);
+            let gap = rng.random_range(columns.0..columns.1);
+            for c in columns.0..columns.1 {
+                self.set_open((split - 1, c), (split, c), c == gap);
+            }
+            self.divide(rng, (rows.0, split), columns);
+            self.divide(rng, (split, rows.1), columns);
+        } else {
+            let split = rng.random_range(columns.0 + 1..columns.1);
+            let gap = rng.random_range(rows.0..rows.1);
+            for r in rows.0..rows.1 {
+                self.set_open((r, split - 1), (r, split), r == gap);
+            }
+            self.divide(rng, rows, (columns.0, split));
+            self.divide(rng, rows, (split, columns.1));
+        }
+    }
+
+    fn braid(&mut self, rng: &mut ChaCha8Rng, loop_ratio: f64) {
+        for r in 0..self.rooms.0 {
+            for c in 0..self.rooms.1 {
+                let room = (r, c);
+                let neighbours = self.neighbours(room);
+                let closed: Vec<(usize, usize)> = neighbours
+                    .iter()
+                    .copied()
+                    .filter(|&n| !self.is_open(room, n))
+                    .collect();
+                if neighbours.len() - closed.len() == 1 && rng.random::<f64>() < loop_ratio
+                    && let Some(&next) = closed.choose(rng) {
+                    self.set_open(room, next, true);
+                }
+            }
+        }
+    }
+}
+
+/// Random fill followed by a few rounds of the 4-5 smoothing rule. Only the
+/// largest open region is kept so every open cell is reachable.
+fn caves(rows: usize, columns: usize, rng: &mut ChaCha8Rng) -> Vec<Vec<bool>> {
+    let mut cells: Vec<Vec<bool>> = (0..rows)
+        .map(|_| (0..columns).map(|_| rng.random::<f64>() < 0.45).collect())
+        .collect();
+    for _ in 0..4 {
+        let mut next = cells.clone();
+        for i in 0..rows {
+            for j in 0..columns {
+                let mut walls = 0;
+                for di in -1..=1 {
+                    for dj in -1..=1 {
+                        if (di, dj) == (0, 0) {
+                            continue;
+                        }
+                        match step((i, j), (di, dj), rows, columns) {
+                            Some(n) if !cells[n.0][n.1] => (),
+                            _ => walls += 1
+                        }
+                    }
+                }
+                next[i][j] = walls >= 5 || (cells[i][j] && walls >= 4);
+            }
+        }
+        cells = next;
+    }
+
+    let mut region = vec![vec![usize::MAX; columns]; rows];
+    let mut largest = (0, 0);
+    let mut id = 0;
+    for i in 0..rows {
+        for j in 0..columns {
+            if cells[i][j] || region[i][j] != usize::MAX {
+                continue;
+            }
+            let dist = distances(&cells, (i, j));
+            let mut size = 0;
+            for (ri, row) in dist.iter().enumerate() {
+                for (rj, &d) in row.iter().enumerate() {
+                    if d != usize::MAX {
+                        region[ri][rj] = id;
+                        size += 1;
+                    }
+                }
+            }
+            if size > largest.1 {
+                largest = (id, size);
+            }
+            id += 1;
+        }
+    }
+    if largest.1 < 2 {
+        // Too small for the automaton to leave a usable cave; fall back to an open room.
+        return vec![vec![false; columns]; rows];
+    }
+    for i in 0..rows {
+        for j in 0..columns {
+            cells[i][j] = region[i][j] != largest.0;
+        }
+    }
+    cells
+}
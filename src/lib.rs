@@ -0,0 +1,23 @@
+//! Tabular reinforcement learning on grid mazes.
+//!
+//! `model` holds the maze environment and its Monte Carlo learner, `generate`
+//! builds mazes procedurally, `maze_file` reads and writes them, `movingai`
+//! imports the Moving AI Lab grid benchmarks and `analysis` compares learned
+//! paths with shortest-path search. `checkpoint` saves a trained agent so
+//! training can be resumed, `metrics` records how each training episode
+//! went and `export` writes those records out for analysis.
+//! `demonstration` stores hand-played episodes an agent can learn from
+//! offline. The eframe editor lives in the `MazeRL` binary behind the `gui`
+//! feature, and `mazerl-train` trains headless from the command line.
+
+pub mod analysis;
+pub mod checkpoint;
+pub mod demonstration;
+pub mod export;
+pub mod generate;
+pub mod maze_file;
+pub mod metrics;
+pub mod model;
+pub mod movingai;
+
+pub use model::{Action, Board, EpisodeStep, MazeError, Position, Rewards, State, Transition, Validation};
@@ -1,25 +1,427 @@
-mod model;
-
 use eframe::egui;
 use egui::Color32;
-use std::collections::HashSet;
+use mazerl::checkpoint::Checkpoint;
+use mazerl::demonstration::{Demonstrations, OfflineUpdate};
+use mazerl::export::{self, Evaluation, RunMetadata};
+use mazerl::maze_file::{CellKind, MazeFile};
+use mazerl::metrics::TrainingHistory;
+use mazerl::{analysis, generate, model, movingai};
+use model::Position;
+use std::cell::{Cell, OnceCell, RefCell};
+use std::collections::{HashSet, VecDeque};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::ops::Range;
 use std::sync::{mpsc, Arc, Mutex};
 use std::thread;
+use std::time::{Duration, Instant};
 
-#[derive(PartialEq)]
+#[derive(Clone, Copy, PartialEq)]
 enum AppMode {
     ConfiguringMaze,
     ConfiguringTraining,
     TrainingLoop,
-    DisplayOutput
+    DisplayOutput,
+    ManualPlay
 }
 
-#[derive(PartialEq)]
+#[derive(Clone, Copy, PartialEq)]
 enum Mode {
     Rest,
-    Hover,
+    Brush,
+    Rectangle,
+    Line,
+    Fill,
     Start,
-    Finish
+    Finish,
+    Charger
+}
+
+/// What colors the open cells of the policy grid.
+#[derive(Clone, Copy, PartialEq, Hash)]
+enum Heatmap {
+    Off,
+    Value,
+    Visits
+}
+
+impl Heatmap {
+    const ALL: [Heatmap; 3] = [Heatmap::Off, Heatmap::Value, Heatmap::Visits];
+
+    fn label(&self) -> &'static str {
+        match self {
+            Heatmap::Off => "No Heatmap",
+            Heatmap::Value => "Value Heatmap",
+            Heatmap::Visits => "Visit Heatmap"
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum LiveMode {
+    FastForward,
+    EveryNth,
+    EveryEpisode
+}
+
+impl LiveMode {
+    const ALL: [LiveMode; 3] = [LiveMode::FastForward, LiveMode::EveryNth, LiveMode::EveryEpisode];
+
+    fn label(&self) -> &'static str {
+        match self {
+            LiveMode::FastForward => "Fast-forward",
+            LiveMode::EveryNth => "Every Nth Episode",
+            LiveMode::EveryEpisode => "Every Episode"
+        }
+    }
+}
+
+/// Shared between the training thread and the UI so training can be watched.
+/// The thread publishes a snapshot of the board and, for watched episodes,
+/// the moves taken; it then waits `step_delay` per move while the UI plays
+/// them back.
+struct Live {
+    mode: LiveMode,
+    every: u32,
+    step_delay: Duration,
+    board: Option<model::Board>,
+    snapshot_at: Instant,
+    episode: Vec<model::EpisodeStep>,
+    episode_number: u64,
+    shown_at: Instant,
+    frozen: Option<usize>
+}
+
+impl Default for Live {
+    fn default() -> Self {
+        Self {
+            mode: LiveMode::FastForward,
+            every: 1000,
+            step_delay: Duration::from_millis(50),
+            board: None,
+            snapshot_at: Instant::now(),
+            episode: Vec::new(),
+            episode_number: 0,
+            shown_at: Instant::now(),
+            frozen: None
+        }
+    }
+}
+
+impl Live {
+    /// Moves of the watched episode played back so far.
+    fn playing(&self) -> bool {
+        self.frozen.is_none() && self.shown_steps() < self.episode.len()
+    }
+
+    /// Stops playback where it is, so the training thread stops waiting on it.
+    fn freeze(&mut self) {
+        self.frozen = Some(self.shown_steps());
+    }
+
+    fn unfreeze(&mut self) {
+        if let Some(shown) = self.frozen.take() {
+            self.shown_at = Instant::now() - self.step_delay * shown as u32;
+        }
+    }
+
+    fn shown_steps(&self) -> usize {
+        if let Some(shown) = self.frozen {
+            return shown.min(self.episode.len());
+        }
+        let elapsed = self.shown_at.elapsed().as_secs_f64();
+        let per_step = self.step_delay.as_secs_f64().max(f64::EPSILON);
+        ((elapsed / per_step) as usize).min(self.episode.len())
+    }
+}
+
+/// Commands from the UI to the training thread.
+#[derive(Clone, Copy, PartialEq)]
+enum Control {
+    Pause,
+    Resume,
+    Cancel,
+    StepEpisode,
+    StepMove
+}
+
+/// A training episode kept for replay.
+struct Replay {
+    label: String,
+    steps: Vec<model::EpisodeStep>
+}
+
+/// Hyperparameters handed to the training thread. Every `record_every`th
+/// episode is kept for replay.
+#[derive(Clone, Copy)]
+struct TrainingRun {
+    episodes: u32,
+    record_every: u32,
+    trajectory_limit: u32,
+    discount_rate: f64,
+    learning_rate: f64,
+    epsilon: f64
+}
+
+/// Trains `board` for `run.episodes` episodes, publishing progress, metrics
+/// and watched episodes as it goes. Between episodes it waits for playback to
+/// finish and obeys `control`; while paused it blocks until told to resume,
+/// step or cancel. Returns the board as trained so far.
+fn train_worker(
+    mut board: model::Board,
+    run: TrainingRun,
+    control: mpsc::Receiver<Control>,
+    progress: &Mutex<f32>,
+    history: &Mutex<TrainingHistory>,
+    live: &Mutex<Live>,
+    replays: &Mutex<Vec<Replay>>
+) -> model::Board {
+    let mut paused = false;
+    let mut trained = 0;
+    while trained < run.episodes {
+        // Resuming can restart a frozen playback, so wait again after it.
+        let single = loop {
+            while live.lock().unwrap().playing() {
+                thread::sleep(Duration::from_millis(10));
+            }
+            let mut single = None;
+            loop {
+                let message = if paused {
+                    control.recv().map_err(|_| mpsc::TryRecvError::Disconnected)
+                } else {
+                    control.try_recv()
+                };
+                match message {
+                    Ok(Control::Pause) => paused = true,
+                    Ok(Control::Resume) => paused = false,
+                    Ok(step @ (Control::StepEpisode | Control::StepMove)) => {
+                        single = Some(step);
+                        break;
+                    },
+                    Ok(Control::Cancel) | Err(mpsc::TryRecvError::Disconnected) => return board,
+                    Err(mpsc::TryRecvError::Empty) => break
+                }
+            }
+            if single.is_some() || !live.lock().unwrap().playing() {
+                break single;
+            }
+        };
+
+        let (mode, every) = {
+            let live = live.lock().unwrap();
+            (live.mode, live.every)
+        };
+        let watched = match (single, mode) {
+            (Some(Control::StepEpisode | Control::StepMove), _) => true,
+            (_, LiveMode::FastForward) => false,
+            (_, LiveMode::EveryNth) => (board.episodes() + 1).is_multiple_of(u64::from(every)),
+            (_, LiveMode::EveryEpisode) => true
+        };
+        let recorded = (board.episodes() + 1).is_multiple_of(u64::from(run.record_every));
+        if recorded && !watched {
+            let (metrics, steps) = board.train_recorded(run.trajectory_limit, run.discount_rate, run.learning_rate, run.epsilon);
+            replays.lock().unwrap().push(Replay { label: replay_label(metrics.episode, &steps, metrics.reached_finish), steps });
+            history.lock().unwrap().extend([metrics]);
+        } else if watched {
+            // Show the values the agent acts on, not the ones it learns from this episode.
+            let before = board.clone();
+            let (metrics, steps) = board.train_recorded(run.trajectory_limit, run.discount_rate, run.learning_rate, run.epsilon);
+            if recorded {
+                replays.lock().unwrap().push(Replay { label: replay_label(metrics.episode, &steps, metrics.reached_finish), steps: steps.clone() });
+            }
+            let mut live = live.lock().unwrap();
+            live.board = Some(before);
+            live.snapshot_at = Instant::now();
+            live.frozen = match single {
+                Some(Control::StepMove) => Some(1),
+                Some(_) => Some(steps.len()),
+                None => None
+            };
+            live.episode = steps;
+            live.episode_number = metrics.episode;
+            live.shown_at = Instant::now();
+            history.lock().unwrap().extend([metrics]);
+        } else {
+            let metrics = board.train(1, run.trajectory_limit, run.discount_rate, run.learning_rate, run.epsilon);
+            history.lock().unwrap().extend(metrics);
+            let mut live = live.lock().unwrap();
+            // A stepped episode shown earlier is stale now.
+            live.episode.clear();
+            if single.is_some() || live.snapshot_at.elapsed() >= SNAPSHOT_INTERVAL {
+                live.board = Some(board.clone());
+                live.snapshot_at = Instant::now();
+            }
+        }
+        trained += 1;
+        *progress.lock().unwrap() = trained as f32 / run.episodes as f32;
+    }
+    board
+}
+
+fn replay_label(episode: u64, steps: &[model::EpisodeStep], reached_finish: bool) -> String {
+    let outcome = if reached_finish { "reached the finish" } else { "did not finish" };
+    format!("Episode {} ({} moves, {})", episode, steps.len(), outcome)
+}
+
+/// How often the heatmap snapshot is refreshed between watched episodes.
+const SNAPSHOT_INTERVAL: Duration = Duration::from_millis(250);
+
+type Path = Vec<model::Transition>;
+/// The board trained on the worker thread and its greedy rollout.
+type Trained = (model::Board, Vec<model::EpisodeStep>);
+
+/// Editor undo entries kept; each holds a full copy of the grid.
+const UNDO_LIMIT: usize = 100;
+
+/// Everything the editor can change, saved before each edit for undo/redo.
+struct Snapshot {
+    cells: Vec<Vec<bool>>,
+    chargers: Vec<Vec<bool>>,
+    start: Option<Position>,
+    finish: Option<Position>
+}
+
+/// Cells on the straight line from `from` to `to`, inclusive (Bresenham).
+fn line(from: Position, to: Position) -> Vec<Position> {
+    let (mut row, mut col) = (from.row as i64, from.col as i64);
+    let (end_row, end_col) = (to.row as i64, to.col as i64);
+    let (d_row, d_col) = ((end_row - row).abs(), -(end_col - col).abs());
+    let (step_row, step_col) = ((end_row - row).signum(), (end_col - col).signum());
+    let mut error = d_row + d_col;
+    let mut cells = vec![from];
+    while (row, col) != (end_row, end_col) {
+        let doubled = 2 * error;
+        if doubled >= d_col {
+            error += d_col;
+            row += step_row;
+        }
+        if doubled <= d_row {
+            error += d_row;
+            col += step_col;
+        }
+        cells.push(Position::new(row as usize, col as usize));
+    }
+    cells
+}
+
+/// Top-left and bottom-right corners of the rectangle spanned by `a` and `b`.
+fn corners(a: Position, b: Position) -> (Position, Position) {
+    (Position::new(a.row.min(b.row), a.col.min(b.col)), Position::new(a.row.max(b.row), a.col.max(b.col)))
+}
+
+/// Distance between neighbouring cell centers on the maze canvas at
+/// 100% zoom, and the most auto-fit will enlarge a small maze to.
+const CELL_SIZE: f32 = 24.0;
+/// Blank space left between painted cells.
+const CELL_GAP: f32 = 2.0;
+const MIN_PITCH: f32 = 0.5;
+const MAX_PITCH: f32 = 96.0;
+/// Tallest the maze canvas grows; larger mazes are zoomed or panned.
+const MAX_CANVAS_HEIGHT: f32 = 480.0;
+/// Below this pitch the maze is drawn as one cached texture instead of a
+/// shape per cell.
+const DETAIL_PITCH: f32 = 8.0;
+
+/// The colors of every cell, kept as a texture until `key` changes.
+struct GridCache {
+    key: u64,
+    colors: Vec<Color32>,
+    texture: egui::TextureHandle
+}
+
+/// Hashes whatever a canvas's colors depend on into a `GridCache` key.
+fn grid_key(parts: impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    parts.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Zoom and pan of the maze canvas. `offset` is where the maze's top-left
+/// corner sits inside the canvas, and `fit` refits the maze every frame.
+#[derive(Clone, Copy)]
+struct View {
+    pitch: f32,
+    offset: egui::Vec2,
+    fit: bool
+}
+
+impl Default for View {
+    fn default() -> Self {
+        Self { pitch: CELL_SIZE, offset: egui::Vec2::ZERO, fit: true }
+    }
+}
+
+/// Where a maze was painted, so overlays and pointer input can be mapped to
+/// cells exactly.
+struct Canvas {
+    rect: egui::Rect,
+    origin: egui::Pos2,
+    pitch: f32,
+    rows: usize,
+    cols: usize
+}
+
+impl Canvas {
+    fn cell_rect(&self, cell: Position) -> egui::Rect {
+        let min = self.origin + egui::vec2(cell.col as f32, cell.row as f32) * self.pitch;
+        let gap = (self.pitch * 0.1).min(CELL_GAP);
+        egui::Rect::from_min_size(min, egui::Vec2::splat(self.pitch)).shrink(gap / 2.0)
+    }
+
+    fn cell_at(&self, pos: egui::Pos2) -> Option<Position> {
+        let offset = (pos - self.origin) / self.pitch;
+        if !self.rect.contains(pos) || offset.x < 0.0 || offset.y < 0.0 {
+            return None;
+        }
+        let cell = Position::new(offset.y as usize, offset.x as usize);
+        cell.in_bounds((self.rows, self.cols)).then_some(cell)
+    }
+
+    /// Rows and columns at least partly inside the canvas, so only those
+    /// need painting.
+    fn visible(&self) -> (Range<usize>, Range<usize>) {
+        let first = (self.rect.min - self.origin) / self.pitch;
+        let last = (self.rect.max - self.origin) / self.pitch;
+        let span = |from: f32, to: f32, count: usize| {
+            (from.floor().max(0.0) as usize).min(count)..(to.ceil().max(0.0) as usize).min(count)
+        };
+        (span(first.y, last.y, self.rows), span(first.x, last.x, self.cols))
+    }
+}
+
+/// Episodes averaged into each point of the learning curves.
+const CURVE_WINDOW: usize = 100;
+
+/// Paints `values` as a line chart, averaging runs of points that share a
+/// pixel column so long histories stay cheap to draw.
+fn chart(ui: &mut egui::Ui, title: &str, values: &[f64], color: Color32) {
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(280.0, 120.0), egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 2.0, Color32::from_gray(30));
+    let plot = rect.shrink2(egui::vec2(6.0, 18.0));
+    let font = egui::FontId::proportional(11.0);
+    painter.text(rect.left_top() + egui::vec2(6.0, 3.0), egui::Align2::LEFT_TOP, title, font.clone(), Color32::LIGHT_GRAY);
+    if values.is_empty() {
+        return;
+    }
+
+    let columns = (plot.width() as usize).max(1);
+    let per_column = values.len().div_ceil(columns);
+    let points: Vec<f64> = values.chunks(per_column).map(|chunk| chunk.iter().sum::<f64>() / chunk.len() as f64).collect();
+    let low = points.iter().copied().fold(f64::INFINITY, f64::min);
+    let high = points.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let span = if high > low { high - low } else { 1.0 };
+    let last = (points.len() - 1).max(1) as f32;
+    let line: Vec<egui::Pos2> = points
+        .iter()
+        .enumerate()
+        .map(|(i, &value)| egui::pos2(
+            plot.left() + plot.width() * i as f32 / last,
+            plot.bottom() - plot.height() * ((value - low) / span) as f32
+        ))
+        .collect();
+    painter.add(egui::Shape::line(line, egui::Stroke::new(1.5, color)));
+    painter.text(rect.right_top() + egui::vec2(-6.0, 3.0), egui::Align2::RIGHT_TOP, format!("{:.3}", high), font.clone(), Color32::GRAY);
+    painter.text(rect.right_bottom() + egui::vec2(-6.0, -3.0), egui::Align2::RIGHT_BOTTOM, format!("{:.3}", low), font, Color32::GRAY);
 }
 
 struct MazeApp {
@@ -28,23 +430,68 @@ struct MazeApp {
     temp_rows: usize,
     temp_cols: usize,
     cells: Vec<Vec<bool>>,
-    hovered: Vec<Vec<bool>>,
+    chargers: Vec<Vec<bool>>,
+    view: Cell<View>,
+    grid: RefCell<Option<GridCache>>,
+    revision: u64,
+    draw_walls: bool,
+    stroke: Option<(Position, Position)>,
+    undo: Vec<Snapshot>,
+    redo: Vec<Snapshot>,
     app_mode: AppMode,
     mode: Mode,
-    start: (usize, usize),
-    finish: (usize, usize),
+    generator: generate::Generator,
+    seed: u64,
+    loop_ratio: f64,
+    start: Option<Position>,
+    finish: Option<Position>,
     board: Option<model::Board>,
+    trained_maze: Option<MazeFile>,
+    file_path: String,
+    map_path: String,
+    scenario_path: String,
+    scenario_index: usize,
+    checkpoint_path: String,
+    export_dir: String,
     error: String,
     training_num: u32,
     trajectory_limit: u32,
     discount_rate: f64,
     learning_rate: f64,
     epsilon: f64,
-    trajectory: Vec<((usize, usize), (usize, usize))>,
+    rewards: model::Rewards,
+    fuel_enabled: bool,
+    fuel_capacity: u32,
+    out_of_fuel_penalty: f64,
+    trajectory: Path,
+    play: Option<model::Board>,
+    play_steps: Vec<model::EpisodeStep>,
+    record_demonstrations: bool,
+    demonstrations: Vec<Vec<model::EpisodeStep>>,
+    demonstrations_path: String,
+    offline_update: OfflineUpdate,
+    greedy: Vec<model::EpisodeStep>,
+    replays: Arc<Mutex<Vec<Replay>>>,
+    record_every: u32,
+    replay_index: usize,
+    replay_step: usize,
+    replay_playing: bool,
+    replay_clock: Instant,
+    replay_delay_ms: u64,
+    heatmap: Heatmap,
+    show_arrows: bool,
+    show_path: bool,
+    oracle: analysis::Search,
+    optimality: Option<analysis::Optimality>,
     currently_training: bool,
-    rx: Option<mpsc::Receiver<Vec<((usize, usize), (usize, usize))>>>,
-    tx: Option<mpsc::Sender<Vec<((usize, usize), (usize, usize))>>>,
-    progress: Arc<Mutex<f32>>
+    rx: Option<mpsc::Receiver<Trained>>,
+    tx: Option<mpsc::Sender<Trained>>,
+    progress: Arc<Mutex<f32>>,
+    control: Option<mpsc::Sender<Control>>,
+    paused: bool,
+    after_training: AppMode,
+    history: Arc<Mutex<TrainingHistory>>,
+    live: Arc<Mutex<Live>>
 }
 
 impl Default for MazeApp {
@@ -58,29 +505,1090 @@ impl Default for MazeApp {
             temp_rows: rows,
             temp_cols: cols,
             cells: vec![vec![false; cols]; rows],
-            hovered: vec![vec![false; cols]; rows],
+            chargers: vec![vec![false; cols]; rows],
+            view: Cell::new(View::default()),
+            grid: RefCell::new(None),
+            revision: 0,
+            draw_walls: true,
+            stroke: None,
+            undo: Vec::new(),
+            redo: Vec::new(),
             app_mode: AppMode::ConfiguringMaze,
             mode: Mode::Rest,
-            start: (0, 0),
-            finish: (0, 0),
+            generator: generate::Generator::Backtracker,
+            seed: 0,
+            loop_ratio: 0.0,
+            start: None,
+            finish: None,
             board: None,
+            trained_maze: None,
+            file_path: String::from("maze.txt"),
+            map_path: String::new(),
+            scenario_path: String::new(),
+            scenario_index: 0,
+            checkpoint_path: String::from("checkpoint.json"),
+            export_dir: String::from("runs"),
             error: String::from(""),
             training_num: 10000,
             trajectory_limit: 1000,
             discount_rate: 1.0,
             learning_rate: 0.1,
             epsilon: 0.9,
+            rewards: model::Rewards::default(),
+            fuel_enabled: false,
+            fuel_capacity: 50,
+            out_of_fuel_penalty: -100.0,
             trajectory: Vec::new(),
+            play: None,
+            play_steps: Vec::new(),
+            record_demonstrations: false,
+            demonstrations: Vec::new(),
+            demonstrations_path: String::from("demonstrations.json"),
+            offline_update: OfflineUpdate::MonteCarlo,
+            greedy: Vec::new(),
+            replays: Arc::new(Mutex::new(Vec::new())),
+            record_every: 1000,
+            replay_index: 0,
+            replay_step: 0,
+            replay_playing: false,
+            replay_clock: Instant::now(),
+            replay_delay_ms: 200,
+            heatmap: Heatmap::Value,
+            show_arrows: true,
+            show_path: true,
+            oracle: analysis::Search::Bfs,
+            optimality: None,
             currently_training: false,
             rx: Some(rx),
             tx: Some(tx),
-            progress: Arc::new(Mutex::new(0.0))
+            progress: Arc::new(Mutex::new(0.0)),
+            control: None,
+            paused: false,
+            after_training: AppMode::DisplayOutput,
+            history: Arc::new(Mutex::new(TrainingHistory::new())),
+            live: Arc::new(Mutex::new(Live::default()))
         }
     }
 }
 
+impl MazeApp {
+    /// Writes the training history and evaluation of the current board.
+    fn export_run(&self) -> Result<String, String> {
+        let (Some(board), Some(optimality)) = (&self.board, &self.optimality) else {
+            return Err(String::from("Nothing to export yet."));
+        };
+        let run = RunMetadata::new(board, self.trajectory_limit, self.discount_rate, self.learning_rate, self.epsilon);
+        let evaluation = Evaluation::new(optimality, self.oracle);
+        export::write_run(&self.export_dir, &run, &self.history.lock().unwrap(), &evaluation)
+            .map(|()| format!("Exported episodes.csv, evaluation.csv and run.jsonl to {}.", self.export_dir))
+            .map_err(|e| format!("{}: {}", self.export_dir, e))
+    }
+
+    fn overlay_toggles(&mut self, ui: &mut egui::Ui, path_label: &str) {
+        ui.horizontal(|ui| {
+            for heatmap in Heatmap::ALL {
+                ui.radio_value(&mut self.heatmap, heatmap, heatmap.label());
+            }
+            ui.separator();
+            ui.checkbox(&mut self.show_arrows, "Greedy Actions");
+            ui.checkbox(&mut self.show_path, path_label);
+        });
+    }
+
+    /// Allocates a zoomable, pannable canvas for the maze and paints it with
+    /// `fill`, which is only called again when `key` changes. Scrolling zooms
+    /// about the pointer; dragging with the right or middle button pans, as
+    /// does the left button when `primary_pans` is set.
+    fn maze_canvas(&self, ui: &mut egui::Ui, primary_pans: bool, key: u64, fill: impl Fn(Position) -> Color32) -> (egui::Response, Canvas) {
+        let mut view = self.view.get();
+        let mut zoom = 1.0;
+        ui.horizontal(|ui| {
+            if ui.button("−").clicked() {
+                zoom = 0.8;
+            }
+            if ui.button("+").clicked() {
+                zoom = 1.25;
+            }
+            if ui.button("Fit").clicked() {
+                view.fit = true;
+            }
+            ui.label(format!("{:.1} px per cell", view.pitch));
+            ui.weak("Scroll to zoom, drag with the right or middle button to pan.");
+        });
+
+        let height = (self.rows as f32 * CELL_SIZE).clamp(2.0 * CELL_SIZE, MAX_CANVAS_HEIGHT);
+        let (response, painter) = ui.allocate_painter(egui::vec2(ui.available_width(), height), egui::Sense::click_and_drag());
+        let rect = response.rect;
+        let mut anchor = rect.center();
+        if let Some(pointer) = response.hover_pos() {
+            let (scroll, pinch) = ui.input(|i| (i.smooth_scroll_delta.y, i.zoom_delta()));
+            let wheel = (scroll * 0.002).exp() * pinch;
+            if wheel != 1.0 {
+                zoom *= wheel;
+                anchor = pointer;
+            }
+        }
+        if zoom != 1.0 {
+            let pitch = (view.pitch * zoom).clamp(MIN_PITCH, MAX_PITCH);
+            let anchor = anchor - rect.min;
+            view.offset = anchor - (anchor - view.offset) * (pitch / view.pitch);
+            view.pitch = pitch;
+            view.fit = false;
+        }
+        let panning = response.dragged_by(egui::PointerButton::Secondary)
+            || response.dragged_by(egui::PointerButton::Middle)
+            || (primary_pans && response.dragged_by(egui::PointerButton::Primary));
+        if panning {
+            view.offset += response.drag_delta();
+            view.fit = false;
+        }
+        let maze = egui::vec2(self.cols as f32, self.rows as f32);
+        if view.fit {
+            view.pitch = (rect.width() / maze.x).min(rect.height() / maze.y).clamp(MIN_PITCH, CELL_SIZE);
+            view.offset = ((rect.size() - maze * view.pitch) / 2.0).max(egui::Vec2::ZERO);
+        }
+        self.view.set(view);
+
+        let canvas = Canvas { rect, origin: rect.min + view.offset, pitch: view.pitch, rows: self.rows, cols: self.cols };
+        painter.rect_filled(rect, 0.0, Color32::from_gray(40));
+
+        let mut grid = self.grid.borrow_mut();
+        if grid.as_ref().is_some_and(|grid| grid.key != key || grid.colors.len() != self.rows * self.cols) {
+            *grid = None;
+        }
+        let grid = grid.get_or_insert_with(|| {
+            let colors: Vec<Color32> = (0..self.rows)
+                .flat_map(|i| (0..self.cols).map(move |j| Position::new(i, j)))
+                .map(&fill)
+                .collect();
+            let image = egui::ColorImage { size: [self.cols, self.rows], pixels: colors.clone() };
+            let texture = ui.ctx().load_texture("maze", image, egui::TextureOptions::NEAREST);
+            GridCache { key, colors, texture }
+        });
+        if view.pitch < DETAIL_PITCH {
+            let area = egui::Rect::from_min_size(canvas.origin, maze * view.pitch);
+            let uv = egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
+            painter.image(grid.texture.id(), area, uv, Color32::WHITE);
+        } else {
+            let (rows, cols) = canvas.visible();
+            for i in rows {
+                for j in cols.clone() {
+                    let cell = Position::new(i, j);
+                    painter.rect_filled(canvas.cell_rect(cell), 2.0, grid.colors[i * self.cols + j]);
+                }
+            }
+        }
+        (response, canvas)
+    }
+
+    /// The maze colored by `board`'s learned values, with greedy actions,
+    /// `path` and the agent at `agent` drawn on top as enabled.
+    fn policy_grid(&self, ui: &mut egui::Ui, board: Option<&model::Board>, path: &[model::Transition], agent: Option<Position>) {
+        // The heatmap only needs its range when the cached colors are rebuilt.
+        let range = OnceCell::new();
+        let key = grid_key((self.revision, board.map(|b| b.episodes()), self.heatmap));
+        let (response, canvas) = self.maze_canvas(ui, true, key, |cell| {
+            let color = self.cell_color(cell.row, cell.col);
+            // Start, finish, walls and chargers keep their colors.
+            let range = *range.get_or_init(|| board.and_then(|board| self.heat_range(board)));
+            match (board.and_then(|b| self.heat(b, cell)), range) {
+                (Some(value), Some(range)) if color == Color32::WHITE => Self::heat_color(value, range),
+                _ => color
+            }
+        });
+
+        let painter = ui.painter_at(canvas.rect);
+        // Arrows are unreadable below this size, so skip them when zoomed out.
+        if self.show_arrows
+            && canvas.pitch >= 10.0
+            && let Some(board) = board {
+            let (rows, cols) = canvas.visible();
+            for i in rows {
+                for j in cols.clone() {
+                    let cell = Position::new(i, j);
+                    if let Some(action) = board.state(cell).greedy_action() {
+                        painter.text(
+                            canvas.cell_rect(cell).center(),
+                            egui::Align2::CENTER_CENTER,
+                            action.arrow(),
+                            egui::FontId::proportional(canvas.pitch * 0.6),
+                            Color32::BLACK
+                        );
+                    }
+                }
+            }
+        }
+        if self.show_path {
+            let stroke = egui::Stroke::new((canvas.pitch * 0.1).clamp(1.0, 3.0), Color32::ORANGE);
+            for (from, to) in path.iter() {
+                let from_pos = canvas.cell_rect(*from).center();
+                let to_pos = canvas.cell_rect(*to).center();
+                painter.line_segment([from_pos, to_pos], stroke);
+            }
+        }
+        if let Some(agent) = agent {
+            painter.circle_filled(canvas.cell_rect(agent).center(), (canvas.pitch * 0.25).max(2.0), Color32::from_rgb(255, 120, 0));
+        }
+        if let Some(board) = board
+            && let Some(cell) = response.hover_pos().and_then(|pos| canvas.cell_at(pos)) {
+            response.on_hover_text_at_pointer(Self::state_summary(board, cell));
+        }
+    }
+
+    fn send_control(&self, message: Control) {
+        if let Some(control) = &self.control {
+            // The thread may already have finished; its result is on the way.
+            let _ = control.send(message);
+        }
+    }
+
+    /// Stops training and keeps the agent as trained so far, going to
+    /// `next` once the thread hands the board back.
+    fn stop_training(&mut self, next: AppMode) {
+        self.live.lock().unwrap().freeze();
+        self.send_control(Control::Cancel);
+        self.after_training = next;
+    }
+
+    fn training_controls(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if self.paused {
+                if ui.button("Resume").clicked() {
+                    self.live.lock().unwrap().unfreeze();
+                    self.send_control(Control::Resume);
+                    self.paused = false;
+                }
+            } else if ui.button("Pause").clicked() {
+                self.live.lock().unwrap().freeze();
+                self.send_control(Control::Pause);
+                self.paused = true;
+            }
+            ui.add_enabled_ui(self.paused, |ui| {
+                if ui.button("Step Episode").clicked() {
+                    self.send_control(Control::StepEpisode);
+                }
+                if ui.button("Step Move").clicked() {
+                    let mut live = self.live.lock().unwrap();
+                    match live.frozen {
+                        Some(shown) if shown < live.episode.len() => live.frozen = Some(shown + 1),
+                        _ => self.send_control(Control::StepMove)
+                    }
+                }
+            });
+            if ui.button("Cancel").clicked() {
+                self.stop_training(AppMode::DisplayOutput);
+            }
+            if ui.button("Back to Training Settings").clicked() {
+                self.stop_training(AppMode::ConfiguringTraining);
+            }
+        });
+    }
+
+    /// Speed controls and the board as the training thread last published it.
+    fn live_view(&mut self, ui: &mut egui::Ui) {
+        {
+            let mut live = self.live.lock().unwrap();
+            ui.horizontal(|ui| {
+                ui.label("Watch:");
+                for mode in LiveMode::ALL {
+                    ui.radio_value(&mut live.mode, mode, mode.label());
+                }
+                ui.add_enabled_ui(live.mode == LiveMode::EveryNth, |ui| {
+                    ui.label("N:");
+                    ui.add(egui::DragValue::new(&mut live.every).speed(10).range(1..=100000));
+                });
+                let mut delay_ms = live.step_delay.as_millis() as u64;
+                ui.label("Step Delay (ms):");
+                if ui.add(egui::DragValue::new(&mut delay_ms).speed(1).range(1..=1000)).changed() {
+                    live.step_delay = Duration::from_millis(delay_ms);
+                }
+            });
+        }
+        self.overlay_toggles(ui, "Episode Path");
+
+        let live = self.live.lock().unwrap();
+        let shown = live.shown_steps();
+        // Stepping freezes an episode to show, even while fast-forwarding.
+        let watching = (live.mode != LiveMode::FastForward || live.frozen.is_some()) && !live.episode.is_empty();
+        if watching {
+            ui.label(format!("Episode {}: step {} of {}", live.episode_number, shown, live.episode.len()));
+        }
+        let (path, agent) = if watching {
+            let path: Path = live.episode[..shown].iter().map(|step| (step.from, step.to)).collect();
+            let agent = live.episode[..shown].last().map_or(live.episode[0].from, |step| step.to);
+            (path, Some(agent))
+        } else {
+            (Vec::new(), None)
+        };
+        self.policy_grid(ui, live.board.as_ref().or(self.board.as_ref()), &path, agent);
+    }
+
+    /// Episode picker, scrubber and playback for the greedy rollout and the
+    /// episodes sampled during training, drawn over the policy grid with the
+    /// details of the current move.
+    fn replay_view(&mut self, ui: &mut egui::Ui) {
+        let (labels, steps) = {
+            let replays = self.replays.lock().unwrap();
+            self.replay_index = self.replay_index.min(replays.len());
+            let mut labels = vec![format!("Greedy rollout ({} moves)", self.greedy.len())];
+            labels.extend(replays.iter().map(|replay| replay.label.clone()));
+            let steps = match self.replay_index {
+                0 => self.greedy.clone(),
+                index => replays[index - 1].steps.clone()
+            };
+            (labels, steps)
+        };
+
+        let delay = Duration::from_millis(self.replay_delay_ms);
+        if self.replay_playing {
+            let due = (self.replay_clock.elapsed().as_millis() / delay.as_millis().max(1)) as usize;
+            if due > 0 {
+                self.replay_step += due;
+                self.replay_clock += delay * due as u32;
+            }
+            if self.replay_step >= steps.len() {
+                self.replay_playing = false;
+            }
+            ui.ctx().request_repaint();
+        }
+        self.replay_step = self.replay_step.min(steps.len());
+
+        ui.horizontal(|ui| {
+            ui.label("Replay:");
+            let before = self.replay_index;
+            egui::ComboBox::from_id_salt("replay")
+                .selected_text(&labels[self.replay_index])
+                .show_ui(ui, |ui| {
+                    for (index, label) in labels.iter().enumerate() {
+                        ui.selectable_value(&mut self.replay_index, index, label);
+                    }
+                });
+            if self.replay_index != before {
+                self.replay_step = 0;
+                self.replay_playing = false;
+            }
+            if self.replay_playing {
+                if ui.button("Pause").clicked() {
+                    self.replay_playing = false;
+                }
+            } else if ui.add_enabled(!steps.is_empty(), egui::Button::new("Play")).clicked() {
+                if self.replay_step >= steps.len() {
+                    self.replay_step = 0;
+                }
+                self.replay_playing = true;
+                self.replay_clock = Instant::now();
+            }
+            if ui.add(egui::Slider::new(&mut self.replay_step, 0..=steps.len()).text("Step")).dragged() {
+                self.replay_playing = false;
+            }
+            ui.label("Delay (ms):");
+            ui.add(egui::DragValue::new(&mut self.replay_delay_ms).speed(5).range(1..=2000));
+        });
+
+        let shown = &steps[..self.replay_step];
+        let path: Path = shown.iter().map(|step| (step.from, step.to)).collect();
+        let agent = shown.last().map(|step| step.to).or(steps.first().map(|step| step.from));
+        self.policy_grid(ui, self.board.as_ref(), &path, agent);
+
+        match steps.get(self.replay_step) {
+            Some(step) => {
+                let fuel = step.fuel.map_or(String::new(), |fuel| format!(" with {} fuel", fuel));
+                ui.label(format!(
+                    "Move {} of {}: at {}{}, take {} to {}, reward {:.2}",
+                    self.replay_step + 1,
+                    steps.len(),
+                    step.from,
+                    fuel,
+                    step.action.arrow(),
+                    step.to,
+                    step.reward
+                ));
+                let state = match (&self.board, step.fuel) {
+                    (Some(board), Some(fuel)) => board.state_with_fuel(step.from, fuel),
+                    (Some(board), None) => Some(board.state(step.from)),
+                    (None, _) => None
+                };
+                if let Some(state) = state {
+                    let values: Vec<String> = state
+                        .actions()
+                        .iter()
+                        .zip(state.action_values())
+                        .map(|(action, value)| format!("{} {:.2}", action.arrow(), value))
+                        .collect();
+                    ui.label(format!("Current Q-values: {}", values.join("   ")));
+                }
+            },
+            None => {
+                let total: f64 = steps.iter().map(|step| step.reward).sum();
+                match steps.last() {
+                    Some(last) => ui.label(format!("Ended at {} after {} moves with return {:.2}.", last.to, steps.len(), total)),
+                    None => ui.label("No moves recorded.")
+                };
+            }
+        }
+    }
+
+    /// Moving averages of the recorded episodes, two charts per row.
+    fn learning_curves(&self, ui: &mut egui::Ui) {
+        let history = self.history.lock().unwrap();
+        let curves = [
+            ("Episode length", history.moving_average(CURVE_WINDOW, |e| e.steps as f64), Color32::from_rgb(230, 160, 60)),
+            ("Return", history.moving_average(CURVE_WINDOW, |e| e.total_reward), Color32::from_rgb(90, 180, 240)),
+            ("Success rate", history.moving_average(CURVE_WINDOW, |e| if e.reached_finish { 1.0 } else { 0.0 }), Color32::from_rgb(100, 210, 100)),
+            ("Max |ΔQ|", history.moving_average(CURVE_WINDOW, |e| e.max_q_change), Color32::from_rgb(220, 100, 200))
+        ];
+        drop(history);
+        for pair in curves.chunks(2) {
+            ui.horizontal(|ui| {
+                for (title, values, color) in pair {
+                    chart(ui, &format!("{} ({}-episode average)", title, CURVE_WINDOW), values, *color);
+                }
+            });
+        }
+    }
+
+    /// Saves the maze for undo before an edit, forgets the redo history and
+    /// marks the cached canvas colors stale.
+    fn record_edit(&mut self) {
+        self.revision += 1;
+        let snapshot = self.snapshot();
+        if self.undo.len() == UNDO_LIMIT {
+            self.undo.remove(0);
+        }
+        self.undo.push(snapshot);
+        self.redo.clear();
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot { cells: self.cells.clone(), chargers: self.chargers.clone(), start: self.start, finish: self.finish }
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.revision += 1;
+        let resized = snapshot.cells.len() != self.rows || snapshot.cells.first().map_or(0, |row| row.len()) != self.cols;
+        self.rows = snapshot.cells.len();
+        self.cols = snapshot.cells.first().map_or(0, |row| row.len());
+        self.temp_rows = self.rows;
+        self.temp_cols = self.cols;
+        self.cells = snapshot.cells;
+        self.chargers = snapshot.chargers;
+        self.start = snapshot.start;
+        self.finish = snapshot.finish;
+        self.stroke = None;
+        if resized {
+            self.view.set(View::default());
+        }
+    }
+
+    fn undo_edit(&mut self) {
+        if let Some(snapshot) = self.undo.pop() {
+            self.redo.push(self.snapshot());
+            self.restore(snapshot);
+        }
+    }
+
+    fn redo_edit(&mut self) {
+        if let Some(snapshot) = self.redo.pop() {
+            self.undo.push(self.snapshot());
+            self.restore(snapshot);
+        }
+    }
+
+    /// Makes `cell` a wall or open cell. The start and finish are never
+    /// walled over, and walls replace chargers.
+    fn set_wall(&mut self, cell: Position, wall: bool) {
+        if wall && (Some(cell) == self.start || Some(cell) == self.finish) {
+            return;
+        }
+        self.revision += 1;
+        self.cells[cell.row][cell.col] = wall;
+        if wall {
+            self.chargers[cell.row][cell.col] = false;
+        }
+    }
+
+    /// Sets every cell connected to `origin` that shares its wall state.
+    fn flood_fill(&mut self, origin: Position, wall: bool) {
+        let target = self.cells[origin.row][origin.col];
+        if target == wall {
+            return;
+        }
+        let mut seen = vec![vec![false; self.cols]; self.rows];
+        let mut queue = VecDeque::from([origin]);
+        seen[origin.row][origin.col] = true;
+        while let Some(cell) = queue.pop_front() {
+            self.set_wall(cell, wall);
+            let neighbours = [
+                cell.row.checked_sub(1).map(|row| Position::new(row, cell.col)),
+                Some(Position::new(cell.row + 1, cell.col)),
+                cell.col.checked_sub(1).map(|col| Position::new(cell.row, col)),
+                Some(Position::new(cell.row, cell.col + 1))
+            ];
+            for next in neighbours.into_iter().flatten() {
+                if next.in_bounds((self.rows, self.cols)) && !seen[next.row][next.col] && self.cells[next.row][next.col] == target {
+                    seen[next.row][next.col] = true;
+                    queue.push_back(next);
+                }
+            }
+        }
+    }
+
+    /// Applies the brush, rectangle, line and fill tools to primary-button
+    /// input on the editor canvas. Each stroke or shape is one undo step, and
+    /// rectangles and lines are previewed until the button is released.
+    fn drawing_tools(&mut self, ui: &egui::Ui, response: &egui::Response, canvas: &Canvas) {
+        let wall = self.draw_walls;
+        let pointer = response.interact_pointer_pos().and_then(|pos| canvas.cell_at(pos));
+        match self.mode {
+            Mode::Brush | Mode::Rectangle | Mode::Line => {
+                if response.clicked()
+                    && let Some(cell) = pointer {
+                    self.record_edit();
+                    self.set_wall(cell, wall);
+                }
+                if response.drag_started_by(egui::PointerButton::Primary)
+                    && let Some(origin) = ui.input(|i| i.pointer.press_origin()).and_then(|pos| canvas.cell_at(pos)) {
+                    self.stroke = Some((origin, origin));
+                    if self.mode == Mode::Brush {
+                        self.record_edit();
+                        self.set_wall(origin, wall);
+                    }
+                }
+                if let Some((anchor, last)) = self.stroke
+                    && let Some(cell) = pointer
+                    && cell != last {
+                    if self.mode == Mode::Brush {
+                        // The pointer can skip cells between frames, so fill the gap.
+                        for cell in line(last, cell) {
+                            self.set_wall(cell, wall);
+                        }
+                    }
+                    self.stroke = Some((anchor, cell));
+                }
+                if let Some((anchor, last)) = self.stroke {
+                    if response.drag_stopped() {
+                        self.stroke = None;
+                        match self.mode {
+                            Mode::Rectangle => {
+                                self.record_edit();
+                                let (min, max) = corners(anchor, last);
+                                for i in min.row..=max.row {
+                                    for j in min.col..=max.col {
+                                        self.set_wall(Position::new(i, j), wall);
+                                    }
+                                }
+                            },
+                            Mode::Line => {
+                                self.record_edit();
+                                for cell in line(anchor, last) {
+                                    self.set_wall(cell, wall);
+                                }
+                            },
+                            _ => ()
+                        }
+                    } else {
+                        let painter = ui.painter_at(canvas.rect);
+                        let color = if wall { Color32::from_rgba_unmultiplied(200, 80, 80, 160) } else { Color32::from_white_alpha(160) };
+                        match self.mode {
+                            Mode::Rectangle => {
+                                let (min, max) = corners(anchor, last);
+                                let area = canvas.cell_rect(min).union(canvas.cell_rect(max));
+                                painter.rect_filled(area, 0.0, color);
+                            },
+                            Mode::Line => {
+                                for cell in line(anchor, last) {
+                                    painter.rect_filled(canvas.cell_rect(cell), 0.0, color);
+                                }
+                            },
+                            _ => ()
+                        }
+                    }
+                }
+            },
+            Mode::Fill => {
+                if response.clicked()
+                    && let Some(cell) = pointer {
+                    self.record_edit();
+                    self.flood_fill(cell, wall);
+                }
+            },
+            _ => self.stroke = None
+        }
+    }
+
+    fn cell_color(&self, i: usize, j: usize) -> Color32 {
+        let cell = Some(Position::new(i, j));
+        if cell == self.start {
+            Color32::from_rgb(80, 200, 80)
+        } else if cell == self.finish {
+            Color32::from_rgb(80, 80, 200)
+        } else if self.cells[i][j] {
+            Color32::from_rgb(200, 80, 80)
+        } else if self.chargers[i][j] {
+            Color32::from_rgb(230, 200, 60)
+        } else {
+            Color32::from_rgb(255, 255, 255)
+        }
+    }
+
+    /// The heatmap quantity at `cell`: the learned state value, or the log of
+    /// the visit count so rarely explored cells still stand out.
+    fn heat(&self, board: &model::Board, cell: Position) -> Option<f64> {
+        match self.heatmap {
+            Heatmap::Off => None,
+            Heatmap::Value => board.state(cell).value(),
+            Heatmap::Visits if board.state(cell).actions().is_empty() => None,
+            Heatmap::Visits => Some((board.cell_visits(cell) as f64).ln_1p())
+        }
+    }
+
+    /// Range of the heatmap quantity over the maze, used to scale colors.
+    fn heat_range(&self, board: &model::Board) -> Option<(f64, f64)> {
+        let (rows, cols) = board.dimensions();
+        let values = (0..rows).flat_map(|i| (0..cols).filter_map(move |j| self.heat(board, Position::new(i, j))));
+        values.fold(None, |range, value| match range {
+            None => Some((value, value)),
+            Some((low, high)) => Some((f64::min(low, value), f64::max(high, value)))
+        })
+    }
+
+    /// Heatmap color for `value` within `range`, from dark purple to yellow.
+    fn heat_color(value: f64, (low, high): (f64, f64)) -> Color32 {
+        let t = if high > low { ((value - low) / (high - low)) as f32 } else { 1.0 };
+        let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+        Color32::from_rgb(mix(68, 253), mix(1, 231), mix(84, 37))
+    }
+
+    /// Hover text listing the action values and visit counts at `cell`,
+    /// like the text dump.
+    fn state_summary(board: &model::Board, cell: Position) -> String {
+        let state = board.state(cell);
+        let mut text = format!("Cell {}", cell);
+        if board.fuel_capacity().is_some() {
+            text.push_str(" (full tank)");
+        }
+        match state.value() {
+            Some(value) => text.push_str(&format!("\nValue: {:.2}", value)),
+            None => text.push_str("\nNo actions")
+        }
+        text.push_str(&format!("\nVisits: {}", board.cell_visits(cell)));
+        for ((action, value), visits) in state.actions().iter().zip(state.action_values()).zip(state.visits()) {
+            text.push_str(&format!("\n{} {:.2} ({} visits)", action.arrow(), value, visits));
+        }
+        text
+    }
+
+    fn cell_set(grid: &[Vec<bool>]) -> HashSet<Position> {
+        let mut set: HashSet<Position> = HashSet::new();
+        for (i, row) in grid.iter().enumerate() {
+            for (j, &flag) in row.iter().enumerate() {
+                if flag {
+                    set.insert(Position::new(i, j));
+                }
+            }
+        }
+        set
+    }
+
+    fn maze_file(&self) -> Option<MazeFile> {
+        let cells = (0..self.rows)
+            .map(|i| {
+                (0..self.cols)
+                    .map(|j| {
+                        if self.cells[i][j] {
+                            CellKind::Wall
+                        } else if self.chargers[i][j] {
+                            CellKind::Charger
+                        } else {
+                            CellKind::Open
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        Some(MazeFile {
+            cells,
+            start: self.start?,
+            finish: self.finish?,
+            rewards: self.rewards
+        })
+    }
+
+    fn set_cells(&mut self, cells: &[Vec<CellKind>], start: Option<Position>, finish: Option<Position>) {
+        self.record_edit();
+        self.rows = cells.len();
+        self.cols = cells.first().map_or(0, |row| row.len());
+        self.temp_rows = self.rows;
+        self.temp_cols = self.cols;
+        self.cells = cells.iter().map(|row| row.iter().map(|&cell| cell == CellKind::Wall).collect()).collect();
+        self.chargers = cells.iter().map(|row| row.iter().map(|&cell| cell == CellKind::Charger).collect()).collect();
+        self.view.set(View::default());
+        self.start = start;
+        self.finish = finish;
+    }
+
+    fn open_maze_file(&mut self, maze: MazeFile) {
+        self.set_cells(&maze.cells, Some(maze.start), Some(maze.finish));
+        self.rewards = maze.rewards;
+    }
+
+    fn import_moving_ai(&mut self) -> Result<String, String> {
+        let cells = movingai::load_map(&self.map_path).map_err(|e| format!("{}: {}", self.map_path, e))?;
+        if self.scenario_path.is_empty() {
+            self.set_cells(&cells, None, None);
+            return Ok(format!("Imported {}. Select a start and finish.", self.map_path));
+        }
+        let scenarios = movingai::load_scenarios(&self.scenario_path).map_err(|e| format!("{}: {}", self.scenario_path, e))?;
+        let scenario = scenarios
+            .get(self.scenario_index)
+            .ok_or_else(|| format!("{} has only {} scenario(s).", self.scenario_path, scenarios.len()))?;
+        let inside = |cell: Position| cell.in_bounds((cells.len(), cells.first().map_or(0, |row| row.len())));
+        if !inside(scenario.start) || !inside(scenario.goal) {
+            return Err(format!("Scenario {} does not fit {}.", self.scenario_index, self.map_path));
+        }
+        self.set_cells(&cells, Some(scenario.start), Some(scenario.goal));
+        Ok(format!("Imported {} with scenario {} (benchmark optimal length {:.2}).", self.map_path, self.scenario_index, scenario.optimal_length))
+    }
+
+    /// Replaces the board with a checkpoint's, showing its maze, rewards and
+    /// fuel budget in the editor.
+    fn load_checkpoint(&mut self) -> Result<String, String> {
+        let located = |e| format!("{}: {}", self.checkpoint_path, e);
+        let checkpoint = Checkpoint::load(&self.checkpoint_path).map_err(located)?;
+        let (blocked, fuel) = (checkpoint.blocked.clone(), checkpoint.fuel.clone());
+        // Validate before touching the editor, so a bad checkpoint changes nothing.
+        let board = model::Board::from_checkpoint(checkpoint).map_err(located)?;
+        let (rows, columns) = board.dimensions();
+        let mut cells = vec![vec![CellKind::Open; columns]; rows];
+        for cell in blocked.iter() {
+            cells[cell.row][cell.col] = CellKind::Wall;
+        }
+        self.rewards = board.rewards();
+        self.fuel_enabled = fuel.is_some();
+        if let Some(fuel) = &fuel {
+            for cell in fuel.chargers.iter() {
+                cells[cell.row][cell.col] = CellKind::Charger;
+            }
+            self.fuel_capacity = board.fuel_capacity().unwrap_or(fuel.capacity);
+            self.out_of_fuel_penalty = fuel.out_of_fuel_penalty;
+        }
+        self.set_cells(&cells, Some(board.start()), Some(board.finish()));
+        let message = format!("Loaded {} after {} episodes.", self.checkpoint_path, board.episodes());
+        self.reset_agent(board);
+        self.trained_maze = self.maze_file();
+        Ok(message)
+    }
+
+    /// A new, untrained board for the maze in the editor.
+    fn fresh_board(&self) -> Result<model::Board, String> {
+        let (Some(start), Some(finish)) = (self.start, self.finish) else {
+            return Err(String::from(if self.start.is_none() {
+                "Please select a starting point before proceeding."
+            } else {
+                "Please select a finishing point before proceeding."
+            }));
+        };
+        let board = model::Board::new(self.rows, self.cols, start, finish, &Self::cell_set(&self.cells)).map_err(|e| e.to_string())?;
+        let validation = board.validate();
+        if validation.is_solvable() {
+            Ok(board)
+        } else {
+            Err(validation.to_string())
+        }
+    }
+
+    fn has_agent(&self) -> bool {
+        self.board.as_ref().is_some_and(|b| b.episodes() > 0)
+    }
+
+    /// Replaces the agent with `board`, dropping everything recorded about
+    /// the old one.
+    fn reset_agent(&mut self, board: model::Board) {
+        self.board = Some(board);
+        self.trained_maze = None;
+        self.history.lock().unwrap().clear();
+        self.trajectory.clear();
+        self.greedy.clear();
+        self.replays.lock().unwrap().clear();
+        self.optimality = None;
+        let mut live = self.live.lock().unwrap();
+        live.board = None;
+        live.episode.clear();
+    }
+
+    /// Moves on to the training settings, keeping the agent if the maze has
+    /// not been edited since it was trained.
+    fn proceed_to_training(&mut self) {
+        if self.has_agent() && self.trained_maze.is_some() && self.maze_file() == self.trained_maze {
+            self.error = String::from("Keeping the trained agent. Use Reset Agent to start over.");
+            self.app_mode = AppMode::ConfiguringTraining;
+            return;
+        }
+        match self.fresh_board() {
+            Ok(board) => {
+                self.error = if self.has_agent() {
+                    String::from("The maze changed, so training starts over with a new agent.")
+                } else {
+                    String::new()
+                };
+                self.reset_agent(board);
+                self.app_mode = AppMode::ConfiguringTraining;
+            },
+            Err(e) => self.error = e
+        }
+    }
+
+    /// A fresh board with the configured rewards and fuel budget.
+    fn configured_board(&self) -> Result<model::Board, String> {
+        let board = self.fresh_board()?.with_rewards(self.rewards);
+        if self.fuel_enabled {
+            board.with_fuel(self.fuel_capacity, &Self::cell_set(&self.chargers), self.out_of_fuel_penalty).map_err(|e| e.to_string())
+        } else {
+            Ok(board)
+        }
+    }
+
+    /// Starts training. An untrained agent is rebuilt with the current
+    /// rewards and fuel budget; a trained one keeps its own and trains more.
+    fn begin_training(&mut self) {
+        if !self.has_agent() {
+            let board = match self.configured_board() {
+                Ok(board) => board,
+                Err(e) => {
+                    self.error = e;
+                    return;
+                }
+            };
+            self.reset_agent(board);
+            self.trained_maze = self.maze_file();
+        }
+        *self.progress.lock().unwrap() = 0.0;
+        self.error.clear();
+        self.app_mode = AppMode::TrainingLoop;
+    }
+
+    fn save_demonstrations(&self) -> Result<String, String> {
+        Demonstrations::new(self.demonstrations.clone())
+            .save(&self.demonstrations_path)
+            .map_err(|e| format!("{}: {}", self.demonstrations_path, e))?;
+        Ok(format!("Saved {} demonstrations to {}.", self.demonstrations.len(), self.demonstrations_path))
+    }
+
+    fn load_demonstrations(&mut self) -> Result<String, String> {
+        let loaded = Demonstrations::load(&self.demonstrations_path).map_err(|e| format!("{}: {}", self.demonstrations_path, e))?;
+        self.demonstrations.extend(loaded.episodes);
+        Ok(format!("Loaded {}; {} demonstrations recorded.", self.demonstrations_path, self.demonstrations.len()))
+    }
+
+    /// Feeds every recorded demonstration to the agent as offline experience,
+    /// starting a fresh agent if there is none yet.
+    fn learn_from_demonstrations(&mut self) -> Result<String, String> {
+        if !self.has_agent() {
+            let board = self.configured_board()?;
+            self.reset_agent(board);
+            self.trained_maze = self.maze_file();
+        }
+        let Some(board) = &mut self.board else {
+            return Err(String::from("No agent to teach."));
+        };
+        let max_change = board
+            .learn_from_demonstrations(&self.demonstrations, self.offline_update, self.discount_rate, self.learning_rate)
+            .map_err(|e| e.to_string())?;
+        Ok(format!(
+            "Learned from {} demonstrations with {} (largest change {:.3}).",
+            self.demonstrations.len(),
+            self.offline_update,
+            max_change
+        ))
+    }
+
+    fn begin_manual_play(&mut self) {
+        match self.configured_board() {
+            Ok(board) => {
+                self.play = Some(board);
+                self.play_steps.clear();
+                self.error.clear();
+                self.app_mode = AppMode::ManualPlay;
+            },
+            Err(e) => self.error = e
+        }
+    }
+
+    /// Moves the manually driven agent, recording the finished episode as a
+    /// demonstration when asked to.
+    fn manual_step(&mut self, action: model::Action) {
+        let Some(play) = &mut self.play else {
+            return;
+        };
+        match play.step(action) {
+            Some(step) => {
+                self.play_steps.push(step);
+                if play.is_done() && self.record_demonstrations {
+                    self.demonstrations.push(self.play_steps.clone());
+                }
+                self.error.clear();
+            },
+            None if !play.is_done() => self.error = format!("Cannot move {} from {}.", action.arrow(), play.position()),
+            None => ()
+        }
+    }
+
+    fn manual_play(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Manual Play");
+        ui.label("Drive the agent with the arrow keys or WASD; press R to restart.");
+
+        if !ui.ctx().wants_keyboard_input() {
+            let keys = [
+                ([egui::Key::ArrowUp, egui::Key::W], model::Action::Up),
+                ([egui::Key::ArrowRight, egui::Key::D], model::Action::Right),
+                ([egui::Key::ArrowDown, egui::Key::S], model::Action::Down),
+                ([egui::Key::ArrowLeft, egui::Key::A], model::Action::Left)
+            ];
+            for (pressed, action) in keys {
+                if ui.input(|i| pressed.iter().any(|&key| i.key_pressed(key))) {
+                    self.manual_step(action);
+                }
+            }
+            if ui.input(|i| i.key_pressed(egui::Key::R)) {
+                self.restart_manual_play();
+            }
+        }
+
+        let Some(play) = &self.play else {
+            return;
+        };
+        let total: f64 = self.play_steps.iter().map(|step| step.reward).sum();
+        ui.horizontal(|ui| {
+            ui.label(format!("Steps: {}", self.play_steps.len()));
+            ui.separator();
+            ui.label(format!("Total reward: {:.2}", total));
+            if let Some(step) = self.play_steps.last() {
+                ui.separator();
+                ui.label(format!("Last reward: {:.2}", step.reward));
+            }
+            if let Some(fuel) = play.fuel_level() {
+                ui.separator();
+                ui.label(format!("Fuel: {}", fuel));
+            }
+        });
+        if play.is_done() {
+            ui.strong(if play.position() == play.finish() { "Reached the finish!" } else { "Out of fuel." });
+        }
+
+        ui.horizontal(|ui| {
+            if ui.button("Restart").clicked() {
+                self.restart_manual_play();
+            }
+            ui.checkbox(&mut self.record_demonstrations, "Record finished episodes as demonstrations");
+            ui.label(format!("{} recorded", self.demonstrations.len()));
+            if ui.add_enabled(!self.demonstrations.is_empty(), egui::Button::new("Clear")).clicked() {
+                self.demonstrations.clear();
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Demonstrations File:");
+            ui.text_edit_singleline(&mut self.demonstrations_path);
+            if ui.button("Save").clicked() {
+                self.error = match self.save_demonstrations() {
+                    Ok(message) | Err(message) => message
+                };
+            }
+            if ui.button("Load").clicked() {
+                self.error = match self.load_demonstrations() {
+                    Ok(message) | Err(message) => message
+                };
+            }
+        });
+
+        ui.separator();
+        if let Some(play) = &self.play {
+            let path: Vec<model::Transition> = self.play_steps.iter().map(|step| (step.from, step.to)).collect();
+            self.policy_grid(ui, None, &path, Some(play.position()));
+        }
+        ui.label(&self.error);
+    }
+
+    fn restart_manual_play(&mut self) {
+        if let Some(play) = &mut self.play {
+            play.reset();
+        }
+        self.play_steps.clear();
+        self.error.clear();
+    }
+
+    /// Back and forward between the maze editor, training settings and
+    /// results, plus the agent's status and a reset. Hidden while training,
+    /// which has its own controls for leaving.
+    fn navigation(&mut self, ctx: &egui::Context) {
+        if self.app_mode == AppMode::TrainingLoop {
+            return;
+        }
+        egui::TopBottomPanel::top("navigation").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                let back = match self.app_mode {
+                    AppMode::ConfiguringTraining | AppMode::ManualPlay => Some(AppMode::ConfiguringMaze),
+                    AppMode::DisplayOutput => Some(AppMode::ConfiguringTraining),
+                    _ => None
+                };
+                let forward = match self.app_mode {
+                    AppMode::ConfiguringMaze => Some(AppMode::ConfiguringTraining),
+                    AppMode::ConfiguringTraining if self.optimality.is_some() => Some(AppMode::DisplayOutput),
+                    _ => None
+                };
+                if ui.add_enabled(back.is_some(), egui::Button::new("◀ Back")).clicked()
+                    && let Some(back) = back {
+                    self.error.clear();
+                    self.app_mode = back;
+                }
+                if ui.add_enabled(forward.is_some(), egui::Button::new("Forward ▶")).clicked() {
+                    match forward {
+                        Some(AppMode::ConfiguringTraining) => self.proceed_to_training(),
+                        Some(next) => {
+                            self.error.clear();
+                            self.app_mode = next;
+                        },
+                        None => ()
+                    }
+                }
+                ui.separator();
+                for (mode, label) in [
+                    (AppMode::ConfiguringMaze, "Maze"),
+                    (AppMode::ConfiguringTraining, "Training"),
+                    (AppMode::DisplayOutput, "Results")
+                ] {
+                    let text = egui::RichText::new(label);
+                    ui.label(if mode == self.app_mode { text.strong() } else { text.weak() });
+                }
+                ui.separator();
+                match self.board.as_ref().map(|b| b.episodes()) {
+                    Some(episodes) if episodes > 0 => {
+                        ui.label(format!("Agent trained for {} episodes", episodes));
+                        if ui.button("Reset Agent").clicked() {
+                            match self.fresh_board() {
+                                Ok(board) => {
+                                    self.reset_agent(board);
+                                    self.error = String::from("Agent reset.");
+                                    if self.app_mode == AppMode::DisplayOutput {
+                                        self.app_mode = AppMode::ConfiguringTraining;
+                                    }
+                                },
+                                Err(e) => self.error = e
+                            }
+                        }
+                    },
+                    _ => {
+                        ui.label("Untrained agent");
+                    }
+                }
+            });
+        });
+    }
+}
+
 impl eframe::App for MazeApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.navigation(ctx);
         match self.app_mode {
             AppMode::ConfiguringMaze => {
                 egui::CentralPanel::default().show(ctx, |ui| {
@@ -88,119 +1596,198 @@ impl eframe::App for MazeApp {
 
                     ui.horizontal(|ui| {
                         ui.label("Rows:");
-                        ui.add(egui::DragValue::new(&mut self.temp_rows).range(2..=30));
+                        ui.add(egui::DragValue::new(&mut self.temp_rows).range(2..=1024));
                         ui.label("Cols:");
-                        ui.add(egui::DragValue::new(&mut self.temp_cols).range(2..=30));
+                        ui.add(egui::DragValue::new(&mut self.temp_cols).range(2..=1024));
 
                         if ui.button("Resize/Reset").clicked() {
+                            self.record_edit();
                             self.rows = self.temp_rows;
                             self.cols = self.temp_cols;
                             self.cells = vec![vec![false; self.cols]; self.rows];
-                            self.hovered = vec![vec![false; self.cols]; self.rows];
-                            self.start = (0, 0);
-                            self.finish = (0, 0);
+                            self.chargers = vec![vec![false; self.cols]; self.rows];
+                            self.view.set(View::default());
+                            self.start = None;
+                            self.finish = None;
+                        }
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.label("File:");
+                        ui.text_edit_singleline(&mut self.file_path);
+
+                        if ui.button("Open").clicked() {
+                            match MazeFile::load(&self.file_path) {
+                                Ok(maze) => {
+                                    self.open_maze_file(maze);
+                                    self.error = format!("Opened {}.", self.file_path);
+                                },
+                                Err(e) => self.error = e.to_string()
+                            }
+                        }
+
+                        if ui.button("Save").clicked() {
+                            self.error = match self.maze_file() {
+                                Some(maze) => match maze.save(&self.file_path) {
+                                    Ok(()) => format!("Saved {}.", self.file_path),
+                                    Err(e) => e.to_string()
+                                },
+                                None => String::from("Please select a starting and finishing point before saving.")
+                            };
+                        }
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.label("Moving AI Map:");
+                        ui.text_edit_singleline(&mut self.map_path);
+                        ui.label("Scenarios:");
+                        ui.text_edit_singleline(&mut self.scenario_path);
+                        ui.label("#");
+                        ui.add(egui::DragValue::new(&mut self.scenario_index));
+
+                        if ui.button("Import").clicked() {
+                            self.error = self.import_moving_ai().unwrap_or_else(|message| message);
+                        }
+                    });
+
+                    ui.horizontal(|ui| {
+                        egui::ComboBox::from_id_salt("generator")
+                            .selected_text(self.generator.to_string())
+                            .show_ui(ui, |ui| {
+                                for generator in generate::Generator::ALL {
+                                    ui.selectable_value(&mut self.generator, generator, generator.to_string());
+                                }
+                            });
+                        ui.label("Seed:");
+                        ui.add(egui::DragValue::new(&mut self.seed));
+                        ui.label("Loop Ratio:");
+                        ui.add(egui::DragValue::new(&mut self.loop_ratio).speed(0.01).range(0.0..=1.0));
+
+                        if ui.button("Generate").clicked() {
+                            let maze = generate::generate(self.generator, self.temp_rows, self.temp_cols, self.seed, self.loop_ratio);
+                            self.record_edit();
+                            self.rows = self.temp_rows;
+                            self.cols = self.temp_cols;
+                            self.cells = maze.cells;
+                            self.chargers = vec![vec![false; self.cols]; self.rows];
+                            self.view.set(View::default());
+                            self.start = Some(maze.start);
+                            self.finish = Some(maze.finish);
                         }
                     });
 
                     ui.horizontal(|ui| {
                         ui.radio_value(&mut self.mode, Mode::Rest, "No Edit");
-                        ui.radio_value(&mut self.mode, Mode::Hover, "Hover Toggle");
+                        ui.radio_value(&mut self.mode, Mode::Brush, "Brush");
+                        ui.radio_value(&mut self.mode, Mode::Rectangle, "Rectangle");
+                        ui.radio_value(&mut self.mode, Mode::Line, "Line");
+                        ui.radio_value(&mut self.mode, Mode::Fill, "Flood Fill");
                         ui.radio_value(&mut self.mode, Mode::Start, "Select Start");
                         ui.radio_value(&mut self.mode, Mode::Finish, "Select Finish");
+                        ui.radio_value(&mut self.mode, Mode::Charger, "Toggle Charger");
                     });
 
-                    ui.separator();
-
-                    egui::Grid::new("maze_grid")
-                        .spacing([5.0, 4.0])
-                        .show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Draw:");
+                        ui.radio_value(&mut self.draw_walls, true, "Walls");
+                        ui.radio_value(&mut self.draw_walls, false, "Open Cells");
+                        ui.separator();
+                        if ui.button("Invert").clicked() {
+                            self.record_edit();
                             for i in 0..self.rows {
                                 for j in 0..self.cols {
-                                    let is_blocked = &mut self.cells[i][j];
-                                    let color = if (i + 1, j + 1) == self.start {
-                                        Color32::from_rgb(80, 200, 80)
-                                    } else {
-                                        if (i + 1, j + 1) == self.finish {
-                                            Color32::from_rgb(80, 80, 200)
-                                        } else {
-                                            if *is_blocked {
-                                                Color32::from_rgb(200, 80, 80)
-                                            } else {
-                                                Color32::from_rgb(255, 255, 255)
-                                            }
-                                        }
-                                    };
-                                    let button = egui::Button::new("").fill(color).min_size(egui::vec2(20.0, 20.0));
-                                    let response = ui.add(button);
-
-                                    let hover_enabled = (self.mode == Mode::Hover)
-                                        && ((i + 1, j + 1) != self.start)
-                                        && ((i + 1, j + 1) != self.finish);
-
-                                    if response.hovered() {
-                                        if hover_enabled {
-                                            if !self.hovered[i][j] {
-                                                *is_blocked = !*is_blocked;
-                                                self.hovered[i][j] = true;
-                                            }
-                                        }
-                                    } else {
-                                        self.hovered[i][j] = false;
-                                    }
-
-                                    if response.clicked() {
-                                        match self.mode {
-                                            Mode::Start => {
-                                                if (i + 1, j + 1) == self.start {
-                                                    self.start = (0, 0);
-                                                } else {
-                                                    if (i + 1, j + 1) != self.finish {
-                                                        self.start = (i + 1, j + 1);
-                                                    }
-                                                }
-                                                self.cells[i][j] = false;
-                                            },
-                                            Mode::Finish => {
-                                                if (i + 1, j + 1) == self.finish {
-                                                    self.finish = (0, 0);
-                                                } else {
-                                                    if (i + 1, j + 1) != self.start {
-                                                        self.finish = (i + 1, j + 1);
-                                                    }
-                                                }
-                                                self.cells[i][j] = false;
-                                            },
-                                            _ => ()
-                                        }
-                                    }
+                                    self.set_wall(Position::new(i, j), !self.cells[i][j]);
                                 }
-                                ui.end_row();
                             }
+                        }
+                        if ui.button("Clear").clicked() {
+                            self.record_edit();
+                            self.cells = vec![vec![false; self.cols]; self.rows];
+                            self.chargers = vec![vec![false; self.cols]; self.rows];
+                        }
+                        ui.separator();
+                        if ui.add_enabled(!self.undo.is_empty(), egui::Button::new("Undo")).on_hover_text("Ctrl+Z").clicked() {
+                            self.undo_edit();
+                        }
+                        if ui.add_enabled(!self.redo.is_empty(), egui::Button::new("Redo")).on_hover_text("Ctrl+Shift+Z or Ctrl+Y").clicked() {
+                            self.redo_edit();
+                        }
+                    });
+
+                    if !ctx.wants_keyboard_input() {
+                        let redo = ctx.input_mut(|i| {
+                            i.consume_shortcut(&egui::KeyboardShortcut::new(egui::Modifiers::COMMAND | egui::Modifiers::SHIFT, egui::Key::Z))
+                                || i.consume_shortcut(&egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Y))
                         });
+                        if redo {
+                            self.redo_edit();
+                        } else if ctx.input_mut(|i| i.consume_shortcut(&egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Z))) {
+                            self.undo_edit();
+                        }
+                    }
 
                     ui.separator();
 
-                    if ui.button("Proceed to Training").clicked() {
-                        if self.start == (0, 0) {
-                            self.error = String::from("Please select a starting point before proceeding.");
-                        } else if self.finish == (0, 0) {
-                            self.error = String::from("Please select a finishing point before proceeding.");
-                        } else {
-                            self.error = String::from("");
-                            let mut set: HashSet<(usize, usize)> = HashSet::new();
-                            for i in 0..self.rows {
-                                for j in 0..self.cols {
-                                    if self.cells[i][j] {
-                                        set.insert((i + 1, j + 1));
-                                    }
+                    let (response, canvas) = self.maze_canvas(ui, self.mode == Mode::Rest, grid_key(self.revision), |cell| self.cell_color(cell.row, cell.col));
+                    self.drawing_tools(ui, &response, &canvas);
+
+                    if response.clicked()
+                        && let Some(position) = response.interact_pointer_pos().and_then(|pos| canvas.cell_at(pos)) {
+                        let (i, j) = (position.row, position.col);
+                        let cell = Some(position);
+                        if matches!(self.mode, Mode::Start | Mode::Finish | Mode::Charger) {
+                            self.record_edit();
+                        }
+                        match self.mode {
+                            Mode::Start => {
+                                if cell == self.start {
+                                    self.start = None;
+                                } else if cell != self.finish {
+                                    self.start = cell;
                                 }
-                            }
-                            self.board = Some(model::Board::new(self.rows, self.cols, self.start, self.finish, &set));
-                            self.app_mode = AppMode::ConfiguringTraining;
+                                self.cells[i][j] = false;
+                            },
+                            Mode::Finish => {
+                                if cell == self.finish {
+                                    self.finish = None;
+                                } else if cell != self.start {
+                                    self.finish = cell;
+                                }
+                                self.cells[i][j] = false;
+                                self.chargers[i][j] = false;
+                            },
+                            Mode::Charger if cell != self.finish => {
+                                self.chargers[i][j] = !self.chargers[i][j];
+                                self.cells[i][j] = false;
+                            },
+                            _ => ()
                         }
                     }
 
-                    ui.label(format!("{}", self.error));
+                    ui.separator();
+
+                    ui.horizontal(|ui| {
+                        if ui.button("Proceed to Training").clicked() {
+                            self.proceed_to_training();
+                        }
+                        if ui.button("Play Manually").clicked() {
+                            self.begin_manual_play();
+                        }
+                    });
+
+                    if ui.button("Validate").clicked() {
+                        if let (Some(start), Some(finish)) = (self.start, self.finish) {
+                            let set = Self::cell_set(&self.cells);
+                            self.error = match model::Board::new(self.rows, self.cols, start, finish, &set) {
+                                Ok(board) => board.validate().to_string(),
+                                Err(e) => e.to_string()
+                            };
+                        } else {
+                            self.error = String::from("Please select a starting and finishing point before validating.");
+                        }
+                    }
+
+                    ui.label(&self.error);
                 });
             },
             AppMode::ConfiguringTraining => {
@@ -211,7 +1798,9 @@ impl eframe::App for MazeApp {
                         ui.label("Training Steps:");
                         ui.add(egui::DragValue::new(&mut self.training_num).speed(1000).range(1..=100000));
                         ui.label("Trajectory Limit:");
-                        ui.add(egui::DragValue::new(&mut self.trajectory_limit).speed(10).range(1..=1000));
+                        ui.add(egui::DragValue::new(&mut self.trajectory_limit).speed(10).range(1..=1000000));
+                        ui.label("Record Every:");
+                        ui.add(egui::DragValue::new(&mut self.record_every).speed(10).range(1..=100000));
                     });
 
                     ui.horizontal(|ui| {
@@ -223,38 +1812,75 @@ impl eframe::App for MazeApp {
                         ui.add(egui::DragValue::new(&mut self.epsilon).speed(0.01).range(0.0..=1.0));
                     });
 
-                    ui.separator();
+                    // A resumed agent keeps the rewards and fuel budget it was trained with.
+                    let resumed = self.board.as_ref().map_or(0, |b| b.episodes());
+                    ui.add_enabled_ui(resumed == 0, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label("Step Reward:");
+                            ui.add(egui::DragValue::new(&mut self.rewards.step).speed(0.1));
+                            ui.label("Finish Reward:");
+                            ui.add(egui::DragValue::new(&mut self.rewards.finish).speed(0.1));
+                        });
 
-                    egui::Grid::new("maze_grid")
-                        .spacing([5.0, 4.0])
-                        .show(ui, |ui| {
-                            for i in 0..self.rows {
-                                for j in 0..self.cols {
-                                    let is_blocked = &mut self.cells[i][j];
-                                    let color = if (i + 1, j + 1) == self.start {
-                                        Color32::from_rgb(80, 200, 80)
-                                    } else {
-                                        if (i + 1, j + 1) == self.finish {
-                                            Color32::from_rgb(80, 80, 200)
-                                        } else {
-                                            if *is_blocked {
-                                                Color32::from_rgb(200, 80, 80)
-                                            } else {
-                                                Color32::from_rgb(255, 255, 255)
-                                            }
-                                        }
-                                    };
-                                    ui.add(egui::Button::new("").fill(color).min_size(egui::vec2(20.0, 20.0)));
-                                }
-                                ui.end_row();
-                            }
+                        ui.horizontal(|ui| {
+                            ui.checkbox(&mut self.fuel_enabled, "Fuel Budget");
+                            ui.add_enabled_ui(self.fuel_enabled, |ui| {
+                                ui.label("Capacity:");
+                                ui.add(egui::DragValue::new(&mut self.fuel_capacity).speed(1).range(1..=1000));
+                                ui.label("Out-of-Fuel Penalty:");
+                                ui.add(egui::DragValue::new(&mut self.out_of_fuel_penalty).speed(1.0).range(-10000.0..=0.0));
+                            });
                         });
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.label("Checkpoint:");
+                        ui.text_edit_singleline(&mut self.checkpoint_path);
+                        if ui.button("Load Checkpoint").clicked() {
+                            self.error = match self.load_checkpoint() {
+                                Ok(message) | Err(message) => message
+                            };
+                        }
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.label("Demonstrations:");
+                        ui.text_edit_singleline(&mut self.demonstrations_path);
+                        if ui.button("Load").clicked() {
+                            self.error = match self.load_demonstrations() {
+                                Ok(message) | Err(message) => message
+                            };
+                        }
+                        egui::ComboBox::from_id_salt("offline_update")
+                            .selected_text(self.offline_update.to_string())
+                            .show_ui(ui, |ui| {
+                                for update in OfflineUpdate::ALL {
+                                    ui.selectable_value(&mut self.offline_update, update, update.to_string());
+                                }
+                            });
+                        let label = format!("Learn from {} Demonstrations", self.demonstrations.len());
+                        if ui.add_enabled(!self.demonstrations.is_empty(), egui::Button::new(label)).clicked() {
+                            self.error = match self.learn_from_demonstrations() {
+                                Ok(message) | Err(message) => message
+                            };
+                        }
+                    });
+
+                    if resumed > 0 {
+                        ui.label(format!("Resuming after {} episodes.", resumed));
+                    }
+
+                    ui.separator();
+
+                    self.maze_canvas(ui, true, grid_key(self.revision), |cell| self.cell_color(cell.row, cell.col));
 
                     ui.separator();
 
                     if ui.button("Begin Training Loop").clicked() {
-                        self.app_mode = AppMode::TrainingLoop;
+                        self.begin_training();
                     }
+
+                    ui.label(&self.error);
                 });
             },
             AppMode::TrainingLoop => {
@@ -263,7 +1889,14 @@ impl eframe::App for MazeApp {
 
                     ui.separator();
 
-                    ui.label("Training...");
+                    let episodes = self.history.lock().unwrap().last().map_or(0, |e| e.episode);
+                    if self.after_training != AppMode::DisplayOutput {
+                        ui.label("Stopping...");
+                    } else if self.paused {
+                        ui.label(format!("Paused after episode {}.", episodes));
+                    } else {
+                        ui.label("Training...");
+                    }
 
                     let progress_value = *self.progress.lock().unwrap();
 
@@ -272,99 +1905,138 @@ impl eframe::App for MazeApp {
                             .show_percentage()
                             .text(format!("{:.1}%", progress_value * 100.0))
                     );
+
+                    self.training_controls(ui);
+
+                    ui.separator();
+
+                    self.live_view(ui);
+
+                    ui.separator();
+
+                    self.learning_curves(ui);
                 });
-                
-                let training_num_c = self.training_num;
-                let trajectory_limit_c = self.trajectory_limit;
-                let discount_rate_c = self.discount_rate;
-                let learning_rate_c = self.learning_rate;
-                let epsilon_c = self.epsilon;
 
                 if !self.currently_training {
                     self.currently_training = true;
+                    self.paused = false;
+                    self.after_training = AppMode::DisplayOutput;
 
+                    let run = TrainingRun {
+                        episodes: self.training_num,
+                        record_every: self.record_every,
+                        trajectory_limit: self.trajectory_limit,
+                        discount_rate: self.discount_rate,
+                        learning_rate: self.learning_rate,
+                        epsilon: self.epsilon
+                    };
                     let board = self.board.clone();
-                    let tx = self.tx.clone().unwrap(); 
+                    let tx = self.tx.clone().unwrap();
+                    let (control_tx, control_rx) = mpsc::channel();
+                    self.control = Some(control_tx);
                     let progress = Arc::clone(&self.progress);
+                    let history = Arc::clone(&self.history);
+                    let live = Arc::clone(&self.live);
+                    let replays = Arc::clone(&self.replays);
+                    {
+                        let mut live = live.lock().unwrap();
+                        live.board = None;
+                        live.episode.clear();
+                        live.frozen = None;
+                    }
 
                     thread::spawn(move || {
-                        if let Some(mut b) = board {
-                            for i in 0..training_num_c {
-                                b.train(1, trajectory_limit_c, discount_rate_c, learning_rate_c, epsilon_c);
-                                {
-                                    let mut p = progress.lock().unwrap();
-                                    *p = (i as f32 + 1.0) / training_num_c as f32;
-                                }
-                            }
-                            let trajectory = b.trajectory(1000, 0.0);
-                            tx.send(trajectory).unwrap();
+                        if let Some(b) = board {
+                            let mut b = train_worker(b, run, control_rx, &progress, &history, &live, &replays);
+                            let greedy = b.rollout(run.trajectory_limit, 0.0);
+                            tx.send((b, greedy)).unwrap();
                         }
                     });
                 }
 
-                if let Some(rx) = &self.rx {
-                    if let Ok(result) = rx.try_recv() {
-                        self.trajectory = result;
-                        self.app_mode = AppMode::DisplayOutput;
-                        self.currently_training = false;
-                    }
+                if let Some(rx) = &self.rx
+                    && let Ok((board, greedy)) = rx.try_recv() {
+                    self.trajectory = greedy.iter().map(|step| (step.from, step.to)).collect();
+                    self.optimality = Some(analysis::optimality(&board, &self.trajectory, self.oracle));
+                    self.board = Some(board);
+                    self.replay_index = 0;
+                    self.replay_step = greedy.len();
+                    self.replay_playing = false;
+                    self.greedy = greedy;
+                    self.error.clear();
+                    self.app_mode = self.after_training;
+                    self.currently_training = false;
+                    self.control = None;
                 }
 
                 ctx.request_repaint();
             },
             AppMode::DisplayOutput => {
                 egui::CentralPanel::default().show(ctx, |ui| {
-                    let cell_size_x = 44.0;
-                    let cell_size_y = 24.0;
-                    let grid_rect = ui.min_rect();
-
                     ui.heading("Maze Editor");
 
+                    self.overlay_toggles(ui, "Replay Path");
+
                     ui.separator();
 
-                    egui::Grid::new("maze_grid")
-                        .spacing([4.0, 4.0])
-                        .show(ui, |ui| {
-                            for i in 0..self.rows {
-                                for j in 0..self.cols {
-                                    let is_blocked = &mut self.cells[i][j];
-                                    let color = if (i + 1, j + 1) == self.start {
-                                        Color32::from_rgb(80, 200, 80)
-                                    } else {
-                                        if (i + 1, j + 1) == self.finish {
-                                            Color32::from_rgb(80, 80, 200)
-                                        } else {
-                                            if *is_blocked {
-                                                Color32::from_rgb(200, 80, 80)
-                                            } else {
-                                                Color32::from_rgb(255, 255, 255)
-                                            }
-                                        }
-                                    };
-                                    ui.add(egui::Button::new("").fill(color).min_size(egui::vec2(20.0, 20.0)));
-                                }
-                                ui.end_row();
-                            }
-                        });
+                    self.replay_view(ui);
+
+                    ui.separator();
 
-                    let painter = ui.painter();
+                    ui.horizontal(|ui| {
+                        ui.label("Oracle:");
+                        let before = self.oracle;
+                        ui.radio_value(&mut self.oracle, analysis::Search::Bfs, analysis::Search::Bfs.to_string());
+                        ui.radio_value(&mut self.oracle, analysis::Search::Dijkstra, analysis::Search::Dijkstra.to_string());
+                        ui.radio_value(&mut self.oracle, analysis::Search::AStar, analysis::Search::AStar.to_string());
+                        if self.oracle != before {
+                            self.optimality = self.board.as_ref().map(|b| analysis::optimality(b, &self.trajectory, self.oracle));
+                        }
+                    });
 
-                    let offset = grid_rect.min.to_vec2();
+                    if let Some(optimality) = &self.optimality {
+                        ui.label(optimality.to_string());
+                    }
 
-                    for (from, to) in self.trajectory.iter() {
-                        let from_pos = egui::pos2(
-                            offset.x + from.1 as f32 * cell_size_x + 10.0,
-                            offset.y + from.0 as f32 * cell_size_y + 42.0,
-                        );
+                    ui.label(self.history.lock().unwrap().to_string());
 
-                        let to_pos = egui::pos2(
-                            offset.x + to.1 as f32 * cell_size_x + 11.0,
-                            offset.y + to.0 as f32 * cell_size_y + 42.0,
-                        );
+                    self.learning_curves(ui);
 
-                        painter.line_segment([from_pos, to_pos], egui::Stroke::new(2.0, egui::Color32::ORANGE));
-                    }
+                    ui.horizontal(|ui| {
+                        ui.label("Episodes:");
+                        ui.add(egui::DragValue::new(&mut self.training_num).speed(1000).range(1..=100000));
+                        if ui.button("Train More").clicked() {
+                            self.begin_training();
+                        }
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.label("Checkpoint:");
+                        ui.text_edit_singleline(&mut self.checkpoint_path);
+                        if ui.button("Save Checkpoint").clicked()
+                            && let Some(board) = &self.board {
+                            self.error = match board.checkpoint().save(&self.checkpoint_path) {
+                                Ok(()) => format!("Saved {} after {} episodes.", self.checkpoint_path, board.episodes()),
+                                Err(e) => format!("{}: {}", self.checkpoint_path, e)
+                            };
+                        }
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.label("Export to:");
+                        ui.text_edit_singleline(&mut self.export_dir);
+                        if ui.button("Export History").clicked() {
+                            self.error = match self.export_run() {
+                                Ok(message) | Err(message) => message
+                            };
+                        }
+                    });
+
+                    ui.label(&self.error);
                 });
+            },
+            AppMode::ManualPlay => {
+                egui::CentralPanel::default().show(ctx, |ui| self.manual_play(ui));
             }
         };
     }
@@ -0,0 +1,309 @@
+use crate::model::{Board, MazeError, Position, Rewards};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+pub const JSON_VERSION: u32 = 1;
+
+#[derive(Debug)]
+pub enum MazeFileError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    UnsupportedVersion(u32),
+    Empty,
+    Ragged { line: usize },
+    UnknownCell { line: usize, column: usize, found: char },
+    MissingStart,
+    MissingFinish,
+    DuplicateStart { line: usize, column: usize },
+    DuplicateFinish { line: usize, column: usize },
+    InvalidHeader { line: usize },
+    InvalidScenario { line: usize }
+}
+
+impl fmt::Display for MazeFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MazeFileError::Io(e) => write!(f, "{}", e),
+            MazeFileError::Json(e) => write!(f, "Invalid maze JSON: {}", e),
+            MazeFileError::UnsupportedVersion(version) => write!(f, "Unsupported maze file version {}.", version),
+            MazeFileError::Empty => write!(f, "The maze file has no rows."),
+            MazeFileError::Ragged { line } => write!(f, "Line {} is not as wide as the first row.", line),
+            MazeFileError::UnknownCell { line, column, found } => write!(f, "Unknown cell {:?} at line {}, column {}.", found, line, column),
+            MazeFileError::MissingStart => write!(f, "The maze has no start (S)."),
+            MazeFileError::MissingFinish => write!(f, "The maze has no finish (G)."),
+            MazeFileError::DuplicateStart { line, column } => write!(f, "Second start at line {}, column {}.", line, column),
+            MazeFileError::DuplicateFinish { line, column } => write!(f, "Second finish at line {}, column {}.", line, column),
+            MazeFileError::InvalidHeader { line } => write!(f, "Invalid map header at line {}.", line),
+            MazeFileError::InvalidScenario { line } => write!(f, "Invalid scenario at line {}.", line)
+        }
+    }
+}
+
+impl std::error::Error for MazeFileError {}
+
+impl From<std::io::Error> for MazeFileError {
+    fn from(e: std::io::Error) -> Self {
+        MazeFileError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for MazeFileError {
+    fn from(e: serde_json::Error) -> Self {
+        MazeFileError::Json(e)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CellKind {
+    #[default]
+    Open,
+    Wall,
+    Charger
+}
+
+impl CellKind {
+    pub fn symbol(&self) -> char {
+        match self {
+            CellKind::Open => '.',
+            CellKind::Wall => '#',
+            CellKind::Charger => 'C'
+        }
+    }
+
+    pub fn from_symbol(symbol: char) -> Option<Self> {
+        match symbol {
+            '.' => Some(CellKind::Open),
+            '#' => Some(CellKind::Wall),
+            'C' => Some(CellKind::Charger),
+            _ => None
+        }
+    }
+}
+
+/// A maze as stored on disk, before it is turned into a `Board`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MazeFile {
+    pub cells: Vec<Vec<CellKind>>,
+    pub start: Position,
+    pub finish: Position,
+    pub rewards: Rewards
+}
+
+#[derive(Serialize, Deserialize)]
+struct JsonMaze {
+    version: u32,
+    rows: usize,
+    columns: usize,
+    start: Position,
+    finish: Position,
+    #[serde(default)]
+    rewards: Rewards,
+    cells: Vec<Vec<CellKind>>
+}
+
+impl MazeFile {
+    pub fn rows(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn columns(&self) -> usize {
+        self.cells.first().map_or(0, |row| row.len())
+    }
+
+    fn positions_of(&self, kind: CellKind) -> HashSet<Position> {
+        let mut set = HashSet::new();
+        for (i, row) in self.cells.iter().enumerate() {
+            for (j, &cell) in row.iter().enumerate() {
+                if cell == kind {
+                    set.insert(Position::new(i, j));
+                }
+            }
+        }
+        set
+    }
+
+    pub fn blocked(&self) -> HashSet<Position> {
+        self.positions_of(CellKind::Wall)
+    }
+
+    pub fn chargers(&self) -> HashSet<Position> {
+        self.positions_of(CellKind::Charger)
+    }
+
+    /// Parses an ASCII grid, one row per line: `#` wall, `.` open, `S` start,
+    /// `G` goal and `C` charger. Blank lines and lines starting with `;` are
+    /// skipped. The start and goal cells are open.
+    pub fn from_ascii(text: &str) -> Result<Self, MazeFileError> {
+        let mut cells: Vec<Vec<CellKind>> = Vec::new();
+        let mut start = None;
+        let mut finish = None;
+        for (index, line) in text.lines().enumerate() {
+            let line_number = index + 1;
+            let line = line.trim_end();
+            if line.is_empty() || line.starts_with(';') {
+                continue;
+            }
+            if cells.first().is_some_and(|first| first.len() != line.chars().count()) {
+                return Err(MazeFileError::Ragged { line: line_number });
+            }
+            let mut row = Vec::new();
+            for (col, ch) in line.chars().enumerate() {
+                let cell = Position::new(cells.len(), col);
+                let kind = match ch {
+                    'S' => {
+                        if start.replace(cell).is_some() {
+                            return Err(MazeFileError::DuplicateStart { line: line_number, column: col + 1 });
+                        }
+                        CellKind::Open
+                    },
+                    'G' => {
+                        if finish.replace(cell).is_some() {
+                            return Err(MazeFileError::DuplicateFinish { line: line_number, column: col + 1 });
+                        }
+                        CellKind::Open
+                    },
+                    found => CellKind::from_symbol(found).ok_or(MazeFileError::UnknownCell { line: line_number, column: col + 1, found })?
+                };
+                row.push(kind);
+            }
+            cells.push(row);
+        }
+        if cells.is_empty() {
+            return Err(MazeFileError::Empty);
+        }
+        Ok(Self {
+            cells,
+            start: start.ok_or(MazeFileError::MissingStart)?,
+            finish: finish.ok_or(MazeFileError::MissingFinish)?,
+            rewards: Rewards::default()
+        })
+    }
+
+    /// Writes the ASCII form. Rewards are not part of it, and a charger under
+    /// the start or goal is written as `S` or `G`.
+    pub fn to_ascii(&self) -> String {
+        let mut text = String::new();
+        for (i, row) in self.cells.iter().enumerate() {
+            for (j, cell) in row.iter().enumerate() {
+                let position = Position::new(i, j);
+                if position == self.start {
+                    text.push('S');
+                } else if position == self.finish {
+                    text.push('G');
+                } else {
+                    text.push(cell.symbol());
+                }
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    pub fn from_json(text: &str) -> Result<Self, MazeFileError> {
+        let json: JsonMaze = serde_json::from_str(text)?;
+        if json.version != JSON_VERSION {
+            return Err(MazeFileError::UnsupportedVersion(json.version));
+        }
+        if json.rows == 0 || json.cells.len() != json.rows {
+            return Err(MazeFileError::Empty);
+        }
+        if let Some(index) = json.cells.iter().position(|row| row.len() != json.columns) {
+            return Err(MazeFileError::Ragged { line: index + 1 });
+        }
+        Ok(Self {
+            cells: json.cells,
+            start: json.start,
+            finish: json.finish,
+            rewards: json.rewards
+        })
+    }
+
+    /// Writes the JSON form with one line per grid row, in the field order
+    /// of `JsonMaze`.
+    pub fn to_json(&self) -> Result<String, MazeFileError> {
+        let rows = self.cells
+            .iter()
+            .map(|row| Ok(format!("    {}", serde_json::to_string(row)?)))
+            .collect::<Result<Vec<String>, MazeFileError>>()?;
+        Ok(format!(
+            "{{\n  \"version\": {},\n  \"rows\": {},\n  \"columns\": {},\n  \"start\": {},\n  \"finish\": {},\n  \"rewards\": {},\n  \"cells\": [\n{}\n  ]\n}}\n",
+            JSON_VERSION,
+            self.rows(),
+            self.columns(),
+            serde_json::to_string(&self.start)?,
+            serde_json::to_string(&self.finish)?,
+            serde_json::to_string(&self.rewards)?,
+            rows.join(",\n")
+        ))
+    }
+
+    /// Reads JSON for `.json` files and the ASCII grid for anything else.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, MazeFileError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+        if is_json(path) {
+            Self::from_json(&text)
+        } else {
+            Self::from_ascii(&text)
+        }
+    }
+
+    /// Writes JSON for `.json` files and the ASCII grid for anything else.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), MazeFileError> {
+        let path = path.as_ref();
+        let text = if is_json(path) { self.to_json()? } else { self.to_ascii() };
+        fs::write(path, text)?;
+        Ok(())
+    }
+
+    pub fn to_board(&self) -> Result<Board, MazeError> {
+        Ok(Board::new(self.rows(), self.columns(), self.start, self.finish, &self.blocked())?.with_rewards(self.rewards))
+    }
+}
+
+fn is_json(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ascii_round_trip() {
+        let text = "S.#\n.C.\n#.G\n";
+        let maze = MazeFile::from_ascii(text).unwrap();
+        assert_eq!(maze.start, Position::new(0, 0));
+        assert_eq!(maze.finish, Position::new(2, 2));
+        assert_eq!(maze.cells[0][2], CellKind::Wall);
+        assert_eq!(maze.cells[1][1], CellKind::Charger);
+        assert_eq!(maze.to_ascii(), text);
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let mut maze = MazeFile::from_ascii("S.#.\n.C.G\n").unwrap();
+        maze.rewards = Rewards { step: -1.0, finish: 10.0 };
+        let json = maze.to_json().unwrap();
+        assert_eq!(MazeFile::from_json(&json).unwrap(), maze);
+        // One line per grid row.
+        assert!(json.contains("\n    [\"open\",\"open\",\"wall\",\"open\"],\n"));
+    }
+
+    #[test]
+    fn rejects_ragged_rows() {
+        assert!(matches!(MazeFile::from_ascii("S..\n.G\n"), Err(MazeFileError::Ragged { line: 2 })));
+        let json = r#"{"version":1,"rows":2,"columns":2,"start":{"row":0,"col":0},"finish":{"row":1,"col":1},"cells":[["open","open"],["open"]]}"#;
+        assert!(matches!(MazeFile::from_json(json), Err(MazeFileError::Ragged { line: 2 })));
+    }
+
+    #[test]
+    fn rejects_duplicate_start_and_finish() {
+        assert!(matches!(MazeFile::from_ascii("S.S\n..G\n"), Err(MazeFileError::DuplicateStart { line: 1, column: 3 })));
+        assert!(matches!(MazeFile::from_ascii("SG\nG.\n"), Err(MazeFileError::DuplicateFinish { line: 2, column: 1 })));
+    }
+}
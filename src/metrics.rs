@@ -0,0 +1,103 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// What happened in one training episode. `max_q_change` is the largest
+/// absolute change the episode made to any action value.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EpisodeMetrics {
+    pub episode: u64,
+    pub steps: u32,
+    pub total_reward: f64,
+    pub discounted_return: f64,
+    pub reached_finish: bool,
+    pub hit_limit: bool,
+    pub max_q_change: f64,
+    pub epsilon: f64
+}
+
+/// Every episode recorded so far, in training order.
+#[derive(Clone, Debug, Default)]
+pub struct TrainingHistory {
+    episodes: Vec<EpisodeMetrics>
+}
+
+impl TrainingHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn episodes(&self) -> &[EpisodeMetrics] {
+        &self.episodes
+    }
+
+    pub fn len(&self) -> usize {
+        self.episodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.episodes.is_empty()
+    }
+
+    pub fn last(&self) -> Option<&EpisodeMetrics> {
+        self.episodes.last()
+    }
+
+    pub fn extend(&mut self, episodes: impl IntoIterator<Item = EpisodeMetrics>) {
+        self.episodes.extend(episodes);
+    }
+
+    pub fn clear(&mut self) {
+        self.episodes.clear();
+    }
+
+    /// Mean of `metric` over the last `window` episodes, or None when empty.
+    pub fn recent_mean(&self, window: usize, metric: impl Fn(&EpisodeMetrics) -> f64) -> Option<f64> {
+        let recent = &self.episodes[self.episodes.len().saturating_sub(window.max(1))..];
+        if recent.is_empty() {
+            None
+        } else {
+            Some(recent.iter().map(metric).sum::<f64>() / recent.len() as f64)
+        }
+    }
+
+    /// Trailing moving average of `metric` with one point per episode.
+    pub fn moving_average(&self, window: usize, metric: impl Fn(&EpisodeMetrics) -> f64) -> Vec<f64> {
+        let window = window.max(1);
+        let mut sum = 0.0;
+        let mut averages = Vec::with_capacity(self.episodes.len());
+        for (i, episode) in self.episodes.iter().enumerate() {
+            sum += metric(episode);
+            if i >= window {
+                sum -= metric(&self.episodes[i - window]);
+            }
+            averages.push(sum / (i + 1).min(window) as f64);
+        }
+        averages
+    }
+}
+
+fn success(episode: &EpisodeMetrics) -> f64 {
+    if episode.reached_finish { 1.0 } else { 0.0 }
+}
+
+impl fmt::Display for TrainingHistory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const WINDOW: usize = 100;
+        let (Some(steps), Some(reward), Some(rate)) = (
+            self.recent_mean(WINDOW, |e| e.steps as f64),
+            self.recent_mean(WINDOW, |e| e.total_reward),
+            self.recent_mean(WINDOW, success)
+        ) else {
+            return write!(f, "No episodes trained yet.");
+        };
+        write!(
+            f,
+            "Last {} of {} episodes: mean steps {:.1} | mean return {:.2} | success rate {:.0}%",
+            WINDOW.min(self.len()),
+            self.len(),
+            steps,
+            reward,
+            rate * 100.0
+        )
+    }
+}
@@ -1,18 +1,43 @@
-use rand::Rng;
-use std::collections::HashSet;
+use crate::checkpoint::{Checkpoint, CheckpointError, FuelBudget, RngState, CHECKPOINT_VERSION};
+use crate::demonstration::{DemonstrationError, OfflineUpdate};
+use crate::metrics::EpisodeMetrics;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
 use std::fmt;
 
+type Step = (Position, usize, Action, f64);
+
+/// How far behavior cloning lifts a demonstrated action above the best
+/// alternative; small so later training can easily change its mind.
+const CLONING_MARGIN: f64 = 1e-3;
+
+/// One move of a rollout as `(from, to)`.
+pub type Transition = (Position, Position);
+
+/// One move of a recorded training episode. `fuel` is the level before the
+/// move, or `None` without a fuel budget.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EpisodeStep {
+    pub from: Position,
+    pub to: Position,
+    pub fuel: Option<u32>,
+    pub action: Action,
+    pub reward: f64
+}
+
 fn round_to(value: f64, decimal_places: u32) -> f64 {
     let multiplier = 10_f64.powi(decimal_places as i32);
     (value * multiplier).round() / multiplier
 }
 
-fn max_index(x: &Vec<f64>) -> usize {
+fn max_index(x: &[f64]) -> usize {
     let mut max_val = x[0];
     let mut max_ind = 0;
-    for i in 0..x.len() {
-        if x[i] > max_val {
-            max_val = x[i];
+    for (i, &value) in x.iter().enumerate() {
+        if value > max_val {
+            max_val = value;
             max_ind = i;
         }
     }
@@ -25,12 +50,7 @@ fn index_of<T: PartialEq>(list: &[T], target: &T) -> Option<usize> {
 
 fn action_formatted(x: Option<&Action>, weight: Option<&f64>) -> String {
     if let Some(action) = x {
-        let action_text = match action {
-            Action::Up => "↑",
-            Action::Right => "→",
-            Action::Down => "↓",
-            Action::Left => "←"
-        };
+        let action_text = action.arrow();
         let weight_format = if let Some(w) = weight {
             format!("{:.1}", w)
         } else {
@@ -42,30 +62,132 @@ fn action_formatted(x: Option<&Action>, weight: Option<&f64>) -> String {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Copy)]
-enum Action {
+/// A 0-based cell coordinate, `row` counted down from the top and `col` from the left.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Position {
+    pub row: usize,
+    pub col: usize
+}
+
+impl Position {
+    pub fn new(row: usize, col: usize) -> Self {
+        Self { row, col }
+    }
+
+    /// Converts a 1-based `(row, col)` pair, returning `None` for a 0 coordinate.
+    pub fn from_one_based(cell: (usize, usize)) -> Option<Self> {
+        Some(Self::new(cell.0.checked_sub(1)?, cell.1.checked_sub(1)?))
+    }
+
+    pub fn to_one_based(self) -> (usize, usize) {
+        (self.row + 1, self.col + 1)
+    }
+
+    pub fn manhattan(self, other: Position) -> usize {
+        self.row.abs_diff(other.row) + self.col.abs_diff(other.col)
+    }
+
+    pub fn in_bounds(self, dimensions: (usize, usize)) -> bool {
+        self.row < dimensions.0 && self.col < dimensions.1
+    }
+
+    /// The neighbouring cell in direction `a`. Moving off the top or left edge
+    /// underflows, so callers only pass actions a `State` allows from `self`.
+    pub(crate) fn moved(self, a: &Action) -> Self {
+        match a {
+            Action::Up => Self::new(self.row - 1, self.col),
+            Action::Right => Self::new(self.row, self.col + 1),
+            Action::Down => Self::new(self.row + 1, self.col),
+            Action::Left => Self::new(self.row, self.col - 1)
+        }
+    }
+}
+
+impl From<(usize, usize)> for Position {
+    fn from(cell: (usize, usize)) -> Self {
+        Self::new(cell.0, cell.1)
+    }
+}
+
+impl From<Position> for (usize, usize) {
+    fn from(position: Position) -> Self {
+        (position.row, position.col)
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({}, {})", self.row, self.col)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Copy, Serialize, Deserialize)]
+pub enum Action {
     Up,
     Right,
     Down,
     Left
 }
 
+impl Action {
+    pub const ALL: [Action; 4] = [Action::Up, Action::Right, Action::Down, Action::Left];
+
+    pub fn arrow(&self) -> &'static str {
+        match self {
+            Action::Up => "↑",
+            Action::Right => "→",
+            Action::Down => "↓",
+            Action::Left => "←"
+        }
+    }
+}
+
+/// The moves available from one cell, their learned action values and how
+/// often each has been taken in training.
 #[derive(Clone, Debug)]
-struct State {
+pub struct State {
     actions: Vec<Action>,
-    action_values: Vec<f64>
+    action_values: Vec<f64>,
+    visits: Vec<u64>
 }
 
 impl State {
-    fn policy (&self, epsilon: f64) -> Action {
-        let mut rng = rand::rng();
+    pub fn actions(&self) -> &[Action] {
+        &self.actions
+    }
+
+    pub fn action_values(&self) -> &[f64] {
+        &self.action_values
+    }
+
+    pub fn visits(&self) -> &[u64] {
+        &self.visits
+    }
+
+    pub fn greedy_action(&self) -> Option<Action> {
+        if self.actions.is_empty() {
+            None
+        } else {
+            Some(self.actions[max_index(&self.action_values)])
+        }
+    }
+
+    /// The largest action value, or `None` for a cell with no moves.
+    pub fn value(&self) -> Option<f64> {
+        self.action_values.iter().copied().reduce(f64::max)
+    }
+
+    fn policy (&self, epsilon: f64, rng: &mut impl Rng) -> Option<Action> {
+        if self.actions.is_empty() {
+            return None;
+        }
         let random_number_1: f64 = rng.random::<f64>();
 
         if random_number_1 < epsilon {
             let random_index_2 = (rng.random::<f64>() * (self.actions.len() as f64)).floor() as usize;
-            return self.actions[random_index_2];
+            Some(self.actions[random_index_2])
         } else {
-            return self.actions[max_index(&self.action_values)];
+            self.greedy_action()
         }
     }
 }
@@ -74,12 +196,7 @@ impl fmt::Display for State {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let acts: Vec<&str> = self.actions
             .iter()
-            .map(|a| match a {
-                Action::Up => "↑",
-                Action::Right => "→",
-                Action::Down => "↓",
-                Action::Left => "←"
-            })
+            .map(|a| a.arrow())
             .collect();
 
         write!(
@@ -91,122 +208,764 @@ impl fmt::Display for State {
     }
 }
 
-#[derive(Clone)]
+/// Reasons a maze cannot be turned into a `Board`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MazeError {
+    EmptyGrid,
+    StartOutOfBounds(Position),
+    FinishOutOfBounds(Position),
+    BlockedOutOfBounds(Position),
+    ChargerOutOfBounds(Position),
+    StartBlocked(Position),
+    FinishBlocked(Position),
+    StartIsFinish(Position),
+    StartEnclosed(Position)
+}
+
+impl fmt::Display for MazeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MazeError::EmptyGrid => write!(f, "The maze needs at least one row and one column."),
+            MazeError::StartOutOfBounds(cell) => write!(f, "Start {} is outside the maze.", cell),
+            MazeError::FinishOutOfBounds(cell) => write!(f, "Finish {} is outside the maze.", cell),
+            MazeError::BlockedOutOfBounds(cell) => write!(f, "Blocked cell {} is outside the maze.", cell),
+            MazeError::ChargerOutOfBounds(cell) => write!(f, "Charger {} is outside the maze.", cell),
+            MazeError::StartBlocked(cell) => write!(f, "Start {} is on a blocked cell.", cell),
+            MazeError::FinishBlocked(cell) => write!(f, "Finish {} is on a blocked cell.", cell),
+            MazeError::StartIsFinish(cell) => write!(f, "Start and finish are both {}.", cell),
+            MazeError::StartEnclosed(cell) => write!(f, "Start {} is walled in on every side.", cell)
+        }
+    }
+}
+
+impl std::error::Error for MazeError {}
+
+/// Name of the learning algorithm used by `Board::train`, as written to
+/// exported run metadata.
+pub const ALGORITHM: &str = "every-visit Monte Carlo";
+
+/// Rewards handed out by the environment on each step.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Rewards {
+    pub step: f64,
+    pub finish: f64
+}
+
+impl Default for Rewards {
+    fn default() -> Self {
+        Self {
+            step: -1.0,
+            finish: 0.0
+        }
+    }
+}
+
+/// Per-episode fuel budget. Every step burns one unit, entering a charger
+/// refills the tank, and running dry away from the finish ends the episode
+/// with `penalty` as the final reward.
+#[derive(Clone, Debug)]
+struct Fuel {
+    capacity: u32,
+    level: u32,
+    chargers: HashSet<Position>,
+    penalty: f64
+}
+
+/// Outcome of `Board::validate`.
+#[derive(Clone, Debug)]
+pub struct Validation {
+    pub finish_reachable: bool,
+    /// Connected groups of open cells that cannot be reached from the start.
+    pub isolated_regions: Vec<Vec<Position>>,
+    /// Open cells walled in on all four sides.
+    pub dead_cells: Vec<Position>
+}
+
+impl Validation {
+    pub fn is_solvable(&self) -> bool {
+        self.finish_reachable
+    }
+}
+
+impl fmt::Display for Validation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts: Vec<String> = Vec::new();
+        if !self.finish_reachable {
+            parts.push(String::from("The finish cannot be reached from the start."));
+        }
+        if !self.isolated_regions.is_empty() {
+            let cells: usize = self.isolated_regions.iter().map(|region| region.len()).sum();
+            parts.push(format!("{} isolated region(s) covering {} cell(s).", self.isolated_regions.len(), cells));
+        }
+        if !self.dead_cells.is_empty() {
+            parts.push(format!("{} dead cell(s) with no moves.", self.dead_cells.len()));
+        }
+        if parts.is_empty() {
+            write!(f, "Maze is valid.")
+        } else {
+            write!(f, "{}", parts.join(" "))
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
 pub struct Board {
-    data: Vec<Vec<State>>,
+    // Indexed `[row][col][fuel level - 1]`; a single layer when there is no fuel budget.
+    data: Vec<Vec<Vec<State>>>,
     dimensions: (usize, usize),
-    start: (usize, usize),
-    finish: (usize, usize),
-    current: (usize, usize)
+    blocked: HashSet<Position>,
+    start: Position,
+    finish: Position,
+    current: Position,
+    rewards: Rewards,
+    fuel: Option<Fuel>,
+    rng: ChaCha8Rng,
+    seed: Option<u64>,
+    episodes: u64
 }
 
 impl Board {
-    pub fn new(rows: usize, columns: usize, start: (usize, usize), finish: (usize, usize), blocked: &HashSet<(usize, usize)>) -> Self {
-        let mut data: Vec<Vec<State>> = Vec::new();
+    /// Builds a board, rejecting out-of-bounds cells, a blocked or enclosed
+    /// start, a blocked finish and a start equal to the finish.
+    pub fn new(rows: usize, columns: usize, start: Position, finish: Position, blocked: &HashSet<Position>) -> Result<Self, MazeError> {
+        let dimensions = (rows, columns);
+        if rows == 0 || columns == 0 {
+            return Err(MazeError::EmptyGrid);
+        }
+        if !start.in_bounds(dimensions) {
+            return Err(MazeError::StartOutOfBounds(start));
+        }
+        if !finish.in_bounds(dimensions) {
+            return Err(MazeError::FinishOutOfBounds(finish));
+        }
+        if let Some(&cell) = blocked.iter().find(|cell| !cell.in_bounds(dimensions)) {
+            return Err(MazeError::BlockedOutOfBounds(cell));
+        }
+        if blocked.contains(&start) {
+            return Err(MazeError::StartBlocked(start));
+        }
+        if blocked.contains(&finish) {
+            return Err(MazeError::FinishBlocked(finish));
+        }
+        if start == finish {
+            return Err(MazeError::StartIsFinish(start));
+        }
+
+        let mut data: Vec<Vec<Vec<State>>> = Vec::new();
         for i in 0..rows {
-            let mut temp: Vec<State> = Vec::new();
+            let mut temp: Vec<Vec<State>> = Vec::new();
             for j in 0..columns {
-                if blocked.contains(&(i + 1, j + 1)) {
-                    temp.push(State {
+                if blocked.contains(&Position::new(i, j)) {
+                    temp.push(vec![State {
                         actions: vec![],
-                        action_values: vec![]
-                    });
+                        action_values: vec![],
+                        visits: vec![]
+                    }]);
                     continue;
                 }
                 let mut actions: Vec<Action> = Vec::new();
                 let mut action_values: Vec<f64> = Vec::new();
-                if i >= 1 && !blocked.contains(&(i, j + 1)) {
+                if i >= 1 && !blocked.contains(&Position::new(i - 1, j)) {
                     actions.push(Action::Up);
                     action_values.push(0.0);
                 }
-                if j <= columns - 2 && !blocked.contains(&(i + 1, j + 2)) {
+                if j + 1 < columns && !blocked.contains(&Position::new(i, j + 1)) {
                     actions.push(Action::Right);
                     action_values.push(0.0);
                 }
-                if i <= rows - 2 && !blocked.contains(&(i + 2, j + 1)) {
+                if i + 1 < rows && !blocked.contains(&Position::new(i + 1, j)) {
                     actions.push(Action::Down);
                     action_values.push(0.0);
                 }
-                if j >= 1 && !blocked.contains(&(i + 1, j)) {
+                if j >= 1 && !blocked.contains(&Position::new(i, j - 1)) {
                     actions.push(Action::Left);
                     action_values.push(0.0);
                 }
-                temp.push(State {
-                    actions: actions,
-                    action_values: action_values
-                });
+                let visits = vec![0; actions.len()];
+                temp.push(vec![State {
+                    actions,
+                    action_values,
+                    visits
+                }]);
             }
             data.push(temp);
         }
-        Self {
-            data: data,
-            dimensions: (rows, columns),
-            start: (start.0 - 1, start.1 - 1),
-            finish: (finish.0 - 1, finish.1 - 1),
-            current: (start.0 - 1, start.1 - 1)
+        if data[start.row][start.col][0].actions.is_empty() {
+            return Err(MazeError::StartEnclosed(start));
+        }
+        Ok(Self {
+            data,
+            dimensions,
+            blocked: blocked.clone(),
+            start,
+            finish,
+            current: start,
+            rewards: Rewards::default(),
+            fuel: None,
+            rng: ChaCha8Rng::from_rng(&mut rand::rng()),
+            seed: None,
+            episodes: 0
+        })
+    }
+
+    /// Adds a fuel budget of `capacity` steps per episode. Entering one of
+    /// `chargers` refills the tank, and `out_of_fuel_penalty` is the reward for
+    /// the step that empties it.
+    pub fn with_fuel(mut self, capacity: u32, chargers: &HashSet<Position>, out_of_fuel_penalty: f64) -> Result<Self, MazeError> {
+        if let Some(&cell) = chargers.iter().find(|cell| !cell.in_bounds(self.dimensions)) {
+            return Err(MazeError::ChargerOutOfBounds(cell));
+        }
+        let capacity = capacity.max(1);
+        for row in self.data.iter_mut() {
+            for col in row.iter_mut() {
+                let layer = col[0].clone();
+                *col = vec![layer; capacity as usize];
+            }
         }
+        self.fuel = Some(Fuel {
+            capacity,
+            level: capacity,
+            chargers: chargers.clone(),
+            penalty: out_of_fuel_penalty
+        });
+        Ok(self)
+    }
+
+    /// Seeds the exploration RNG so training runs can be reproduced.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = ChaCha8Rng::seed_from_u64(seed);
+        self.seed = Some(seed);
+        self
+    }
+
+    /// The seed given to `with_seed`, kept across checkpoints, or `None` when
+    /// exploration was seeded from the OS.
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    pub fn with_rewards(mut self, rewards: Rewards) -> Self {
+        self.rewards = rewards;
+        self
+    }
+
+    pub fn rewards(&self) -> Rewards {
+        self.rewards
+    }
+
+    /// Episodes trained so far, including any before a checkpoint was taken.
+    pub fn episodes(&self) -> u64 {
+        self.episodes
+    }
+
+    pub fn dimensions(&self) -> (usize, usize) {
+        self.dimensions
+    }
+
+    pub fn start(&self) -> Position {
+        self.start
+    }
+
+    pub fn finish(&self) -> Position {
+        self.finish
+    }
+
+    fn layer_state(&self, position: Position, layer: usize) -> &State {
+        &self.data[position.row][position.col][layer]
+    }
+
+    /// The state at `position` with a full tank, or the only state when there is no fuel budget.
+    pub fn state(&self, position: Position) -> &State {
+        let layers = &self.data[position.row][position.col];
+        &layers[layers.len() - 1]
+    }
+
+    /// Moves taken from `position` during training, over every fuel level
+    /// and action. Per-action counts are in `State::visits`.
+    pub fn cell_visits(&self, position: Position) -> u64 {
+        self.data[position.row][position.col].iter().flat_map(|state| state.visits.iter()).sum()
+    }
+
+    /// The state at `position` with `fuel` units left, or `None` if that level does not exist.
+    pub fn state_with_fuel(&self, position: Position, fuel: u32) -> Option<&State> {
+        self.fuel.as_ref()?;
+        self.data[position.row][position.col].get((fuel as usize).checked_sub(1)?)
+    }
+
+    /// FNV-1a hash of the layout: dimensions, start, finish, walls and, with
+    /// a fuel budget, chargers. Stable across runs and platforms, so exports
+    /// from the same maze can be grouped.
+    pub fn maze_hash(&self) -> u64 {
+        let mut words = vec![self.dimensions.0, self.dimensions.1, self.start.row, self.start.col, self.finish.row, self.finish.col];
+        let mut sorted = |cells: &HashSet<Position>| {
+            let mut cells: Vec<Position> = cells.iter().copied().collect();
+            cells.sort();
+            words.push(cells.len());
+            words.extend(cells.iter().flat_map(|cell| [cell.row, cell.col]));
+        };
+        sorted(&self.blocked);
+        if let Some(fuel) = &self.fuel {
+            sorted(&fuel.chargers);
+        }
+        words
+            .iter()
+            .flat_map(|word| (*word as u64).to_le_bytes())
+            .fold(0xcbf29ce484222325, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3))
+    }
+
+    pub fn fuel_capacity(&self) -> Option<u32> {
+        self.fuel.as_ref().map(|fuel| fuel.capacity)
+    }
+
+    pub fn is_blocked(&self, position: Position) -> bool {
+        self.blocked.contains(&position)
+    }
+
+    pub fn is_charger(&self, position: Position) -> bool {
+        self.fuel.as_ref().is_some_and(|fuel| fuel.chargers.contains(&position))
+    }
+
+    fn state_mut(&mut self, position: Position, layer: usize) -> &mut State {
+        &mut self.data[position.row][position.col][layer]
+    }
+
+    /// Cells reachable in one step from `cell`.
+    pub fn neighbours(&self, cell: Position) -> Vec<Position> {
+        self.layer_state(cell, 0).actions.iter().map(|a| cell.moved(a)).collect()
+    }
+
+    fn reachable_from(&self, from: Position, seen: &mut [Vec<bool>]) -> Vec<Position> {
+        let mut region = vec![from];
+        let mut queue = VecDeque::from([from]);
+        seen[from.row][from.col] = true;
+        while let Some(cell) = queue.pop_front() {
+            for next in self.neighbours(cell) {
+                if !seen[next.row][next.col] {
+                    seen[next.row][next.col] = true;
+                    region.push(next);
+                    queue.push_back(next);
+                }
+            }
+        }
+        region
+    }
+
+    /// Checks the maze with a breadth-first search from the start before any
+    /// training time is spent on it.
+    pub fn validate(&self) -> Validation {
+        let mut seen = vec![vec![false; self.dimensions.1]; self.dimensions.0];
+        self.reachable_from(self.start, &mut seen);
+        let finish_reachable = seen[self.finish.row][self.finish.col];
+
+        let mut isolated_regions = Vec::new();
+        let mut dead_cells = Vec::new();
+        for i in 0..self.dimensions.0 {
+            for j in 0..self.dimensions.1 {
+                let cell = Position::new(i, j);
+                if seen[i][j] || self.blocked.contains(&cell) {
+                    continue;
+                }
+                if self.layer_state(cell, 0).actions.is_empty() {
+                    seen[i][j] = true;
+                    dead_cells.push(cell);
+                } else {
+                    isolated_regions.push(self.reachable_from(cell, &mut seen));
+                }
+            }
+        }
+        Validation {
+            finish_reachable,
+            isolated_regions,
+            dead_cells
+        }
+    }
+
+    fn layer(&self) -> usize {
+        self.fuel.as_ref().map_or(0, |fuel| fuel.level as usize - 1)
+    }
+
+    fn out_of_fuel(&self) -> bool {
+        self.fuel.as_ref().is_some_and(|fuel| fuel.level == 0)
+    }
+
+    fn is_terminal(&self) -> bool {
+        self.current == self.finish || self.out_of_fuel()
     }
 
     fn world_model(&mut self, a: &Action) -> f64 {
-        match a {
-            Action::Up => self.current = (self.current.0 - 1, self.current.1),
-            Action::Right => self.current = (self.current.0, self.current.1 + 1),
-            Action::Down => self.current = (self.current.0 + 1, self.current.1),
-            Action::Left => self.current = (self.current.0, self.current.1 - 1)
+        self.current = self.current.moved(a);
+        if let Some(fuel) = &mut self.fuel {
+            fuel.level -= 1;
+            if fuel.chargers.contains(&self.current) {
+                fuel.level = fuel.capacity;
+            }
+        }
+        if self.current == self.finish {
+            self.rewards.finish
+        } else if let Some(fuel) = self.fuel.as_ref().filter(|fuel| fuel.level == 0) {
+            fuel.penalty
+        } else {
+            self.rewards.step
         }
-        if self.current == self.finish { 0.0 } else { -1.0 }
     }
 
-    fn update_after_trajectory(&mut self, trajectory: &Vec<((usize, usize), Action, f64)>, discount_rate: f64, learning_rate: f64) {
-        let mut returns: Vec<f64> = vec![0.0];
-        for i in 1..trajectory.len() {
-            returns.push(round_to(returns[i - 1] * discount_rate + (trajectory[trajectory.len() - i - 1].2 as f64), 5));
+    /// Applies the Monte Carlo update and returns the largest absolute change
+    /// it made to an action value.
+    fn update_after_trajectory(&mut self, trajectory: &[Step], discount_rate: f64, learning_rate: f64) -> f64 {
+        let mut max_change: f64 = 0.0;
+        // G_t = r_t + γ·G_{t+1}, so the final reward (finish or out of fuel) is included.
+        let mut returns: Vec<f64> = vec![0.0; trajectory.len()];
+        let mut current_return = 0.0;
+        for (i, step) in trajectory.iter().enumerate().rev() {
+            current_return = round_to(current_return * discount_rate + step.3, 5);
+            returns[i] = current_return;
         }
-        for i in 0..trajectory.len() {
-            let current_traj = &trajectory[i];
-            let current_state = &mut self.data[current_traj.0.0][current_traj.0.1];
-            if let Some(index) = index_of(&current_state.actions, &current_traj.1) {
-                current_state.action_values[index] += (returns[returns.len() - 1 - i] - current_state.action_values[index]) * learning_rate;
+        for (i, current_traj) in trajectory.iter().enumerate() {
+            let current_state = self.state_mut(current_traj.0, current_traj.1);
+            if let Some(index) = index_of(&current_state.actions, &current_traj.2) {
+                current_state.visits[index] += 1;
+                let change = (returns[i] - current_state.action_values[index]) * learning_rate;
+                current_state.action_values[index] += change;
+                max_change = max_change.max(change.abs());
             }
         }
+        max_change
     }
 
-    fn reset(&mut self) {
+    /// Puts the agent back on the start with a full tank.
+    pub fn reset(&mut self) {
         self.current = self.start;
+        if let Some(fuel) = &mut self.fuel {
+            fuel.level = fuel.capacity;
+        }
     }
 
-    pub fn train(&mut self, num: u32, trajectory_limit: u32, discount_rate: f64, learning_rate: f64, epsilon: f64) {
-        for _ in 0..num {
-            let mut count = 0;
-            let mut traj: Vec<((usize, usize), Action, f64)> = Vec::new();
-            while self.current != self.finish && count < trajectory_limit {
-                let current_state = &self.data[self.current.0][self.current.1];
-                let curr = self.current;
-                let action = current_state.policy(epsilon);
-                let reward = self.world_model(&action);
-                traj.push((curr, action, reward));
-                count += 1;
-            }
-            self.update_after_trajectory(&traj, discount_rate, learning_rate);
-            self.reset();
+    fn episode(&mut self, trajectory_limit: u32, discount_rate: f64, learning_rate: f64, epsilon: f64) -> (EpisodeMetrics, Vec<Step>) {
+        let mut count = 0;
+        let mut traj: Vec<Step> = Vec::new();
+        while !self.is_terminal() && count < trajectory_limit {
+            let layer = self.layer();
+            let curr = self.current;
+            let Some(action) = self.data[curr.row][curr.col][layer].policy(epsilon, &mut self.rng) else {
+                break;
+            };
+            let reward = self.world_model(&action);
+            traj.push((curr, layer, action, reward));
+            count += 1;
+        }
+        let reached_finish = self.current == self.finish;
+        let out_of_fuel = self.out_of_fuel();
+        let max_q_change = self.update_after_trajectory(&traj, discount_rate, learning_rate);
+        self.reset();
+        self.episodes += 1;
+        let mut discount = 1.0;
+        let mut discounted_return = 0.0;
+        for step in traj.iter() {
+            discounted_return += discount * step.3;
+            discount *= discount_rate;
+        }
+        let metrics = EpisodeMetrics {
+            episode: self.episodes,
+            steps: count,
+            total_reward: traj.iter().map(|step| step.3).sum(),
+            discounted_return,
+            reached_finish,
+            hit_limit: !reached_finish && !out_of_fuel && count == trajectory_limit,
+            max_q_change,
+            epsilon
+        };
+        (metrics, traj)
+    }
+
+    /// Where the agent currently is, when driving it with `step`.
+    pub fn position(&self) -> Position {
+        self.current
+    }
+
+    /// Fuel left in the tank, or `None` without a fuel budget.
+    pub fn fuel_level(&self) -> Option<u32> {
+        self.fuel.as_ref().map(|fuel| fuel.level)
+    }
+
+    /// Whether the agent has reached the finish or run out of fuel.
+    pub fn is_done(&self) -> bool {
+        self.is_terminal()
+    }
+
+    /// Moves the agent one step with `action`, without learning. Returns
+    /// `None` when the episode is over or the move is not allowed from the
+    /// current cell; call `reset` to start a new episode.
+    pub fn step(&mut self, action: Action) -> Option<EpisodeStep> {
+        if self.is_terminal() {
+            return None;
+        }
+        let layer = self.layer();
+        let from = self.current;
+        if !self.data[from.row][from.col][layer].actions.contains(&action) {
+            return None;
         }
+        let reward = self.world_model(&action);
+        Some(EpisodeStep {
+            from,
+            to: self.current,
+            fuel: self.fuel.is_some().then_some(layer as u32 + 1),
+            action,
+            reward
+        })
+    }
+
+    /// Trains for `num` episodes and returns the metrics of each.
+    pub fn train(&mut self, num: u32, trajectory_limit: u32, discount_rate: f64, learning_rate: f64, epsilon: f64) -> Vec<EpisodeMetrics> {
+        (0..num).map(|_| self.episode(trajectory_limit, discount_rate, learning_rate, epsilon).0).collect()
+    }
+
+    /// Trains one episode and also returns its moves, for watching or
+    /// replaying it.
+    pub fn train_recorded(&mut self, trajectory_limit: u32, discount_rate: f64, learning_rate: f64, epsilon: f64) -> (EpisodeMetrics, Vec<EpisodeStep>) {
+        let (metrics, traj) = self.episode(trajectory_limit, discount_rate, learning_rate, epsilon);
+        (metrics, self.recorded(traj))
     }
 
-    pub fn trajectory(&mut self, trajectory_limit: u32, epsilon: f64) -> Vec<((usize, usize), (usize, usize))> {
-        let mut final_vec: Vec<((usize, usize), (usize, usize))> = Vec::new();
+    fn recorded(&self, traj: Vec<Step>) -> Vec<EpisodeStep> {
+        let fueled = self.fuel.is_some();
+        traj
+            .into_iter()
+            .map(|(from, layer, action, reward)| EpisodeStep {
+                from,
+                to: from.moved(&action),
+                fuel: fueled.then_some(layer as u32 + 1),
+                action,
+                reward
+            })
+            .collect()
+    }
+
+    /// Follows the ε-greedy policy without learning, recording each move.
+    pub fn rollout(&mut self, trajectory_limit: u32, epsilon: f64) -> Vec<EpisodeStep> {
+        let mut traj: Vec<Step> = Vec::new();
         let mut count = 0;
-        while self.current != self.finish && count < trajectory_limit {
-            let _current_state = &self.data[self.current.0][self.current.1];
-            let curr = self.current.clone();
-            let _action = _current_state.policy(epsilon);
-            let _reward = self.world_model(&_action);
-            let next = self.current.clone();
-            final_vec.push((curr, next));
+        while !self.is_terminal() && count < trajectory_limit {
+            let layer = self.layer();
+            let curr = self.current;
+            let Some(action) = self.data[curr.row][curr.col][layer].policy(epsilon, &mut self.rng) else {
+                break;
+            };
+            let reward = self.world_model(&action);
+            traj.push((curr, layer, action, reward));
             count += 1;
         }
         self.reset();
-        //println!("{}", self);
-        final_vec
+        self.recorded(traj)
+    }
+
+    pub fn trajectory(&mut self, trajectory_limit: u32, epsilon: f64) -> Vec<Transition> {
+        self.rollout(trajectory_limit, epsilon).iter().map(|step| (step.from, step.to)).collect()
+    }
+}
+
+impl Board {
+    /// Learns offline from a recorded episode, such as one played by hand,
+    /// and returns the largest absolute change made to an action value. The
+    /// episode counts towards `episodes` and the visit counts.
+    pub fn learn_from_demonstration(&mut self, demonstration: &[EpisodeStep], update: OfflineUpdate, discount_rate: f64, learning_rate: f64) -> Result<f64, DemonstrationError> {
+        let traj = self.demonstrated(demonstration).map_err(|step| DemonstrationError::Mismatch { episode: 0, step })?;
+        Ok(self.learn_offline(&traj, update, discount_rate, learning_rate))
+    }
+
+    /// Learns from several recorded episodes in order, as
+    /// `learn_from_demonstration` does. Every episode is checked first, so
+    /// one that does not fit leaves the board untouched.
+    pub fn learn_from_demonstrations(&mut self, demonstrations: &[Vec<EpisodeStep>], update: OfflineUpdate, discount_rate: f64, learning_rate: f64) -> Result<f64, DemonstrationError> {
+        let trajectories = demonstrations
+            .iter()
+            .enumerate()
+            .map(|(episode, demonstration)| self.demonstrated(demonstration).map_err(|step| DemonstrationError::Mismatch { episode, step }))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(trajectories
+            .iter()
+            .map(|traj| self.learn_offline(traj, update, discount_rate, learning_rate))
+            .fold(0.0, f64::max))
+    }
+
+    fn learn_offline(&mut self, traj: &[Step], update: OfflineUpdate, discount_rate: f64, learning_rate: f64) -> f64 {
+        let max_change = match update {
+            OfflineUpdate::MonteCarlo => self.update_after_trajectory(traj, discount_rate, learning_rate),
+            OfflineUpdate::QLearning => self.replay_q_learning(traj, discount_rate, learning_rate),
+            OfflineUpdate::BehaviorCloning => self.clone_behavior(traj)
+        };
+        self.episodes += 1;
+        max_change
+    }
+
+    /// Converts recorded moves back into training steps, checking that each
+    /// starts from a state of this board with the recorded action available,
+    /// lands where that action leads, and starts where the previous move
+    /// ended. Rewards are taken from the recording as they are. Fails with the
+    /// index of the first step that does not fit.
+    fn demonstrated(&self, demonstration: &[EpisodeStep]) -> Result<Vec<Step>, usize> {
+        let mut previous: Option<Position> = None;
+        demonstration
+            .iter()
+            .enumerate()
+            .map(|(i, step)| {
+                let layer = match (step.fuel, self.fuel_capacity()) {
+                    (None, None) => Some(0),
+                    (Some(level), Some(capacity)) if (1..=capacity).contains(&level) => Some(level as usize - 1),
+                    _ => None
+                };
+                let connected = previous.is_none_or(|to| to == step.from);
+                previous = Some(step.to);
+                match layer {
+                    Some(layer)
+                        if connected
+                            && step.from.in_bounds(self.dimensions)
+                            && self.layer_state(step.from, layer).actions.contains(&step.action)
+                            && step.to == step.from.moved(&step.action) => {
+                        Ok((step.from, layer, step.action, step.reward))
+                    },
+                    _ => Err(i)
+                }
+            })
+            .collect()
+    }
+
+    /// The cell and fuel layer `step` leads to, or `None` when it ends the
+    /// episode. Mirrors `world_model`.
+    fn successor(&self, step: &Step) -> Option<(Position, usize)> {
+        let to = step.0.moved(&step.2);
+        let level = self.fuel.as_ref().map(|fuel| if fuel.chargers.contains(&to) { fuel.capacity } else { step.1 as u32 });
+        if to == self.finish || level == Some(0) {
+            None
+        } else {
+            Some((to, level.map_or(0, |level| level as usize - 1)))
+        }
+    }
+
+    fn replay_q_learning(&mut self, trajectory: &[Step], discount_rate: f64, learning_rate: f64) -> f64 {
+        let mut max_change: f64 = 0.0;
+        for step in trajectory.iter().rev() {
+            let next_value = self
+                .successor(step)
+                .and_then(|(cell, layer)| self.layer_state(cell, layer).value())
+                .unwrap_or(0.0);
+            let state = self.state_mut(step.0, step.1);
+            if let Some(index) = index_of(&state.actions, &step.2) {
+                state.visits[index] += 1;
+                let change = (step.3 + discount_rate * next_value - state.action_values[index]) * learning_rate;
+                state.action_values[index] += change;
+                max_change = max_change.max(change.abs());
+            }
+        }
+        max_change
+    }
+
+    fn clone_behavior(&mut self, trajectory: &[Step]) -> f64 {
+        let mut max_change: f64 = 0.0;
+        for step in trajectory.iter() {
+            let state = self.state_mut(step.0, step.1);
+            if let Some(index) = index_of(&state.actions, &step.2) {
+                state.visits[index] += 1;
+                let best_other = state
+                    .action_values
+                    .iter()
+                    .enumerate()
+                    .filter(|&(i, _)| i != index)
+                    .map(|(_, &value)| value)
+                    .reduce(f64::max);
+                if let Some(best) = best_other
+                    && state.action_values[index] <= best {
+                    let change = best + CLONING_MARGIN - state.action_values[index];
+                    state.action_values[index] += change;
+                    max_change = max_change.max(change);
+                }
+            }
+        }
+        max_change
+    }
+}
+
+impl Board {
+    /// Captures the maze, the learned values, visit counts, RNG state and
+    /// episode counter so training can be resumed later.
+    pub fn checkpoint(&self) -> Checkpoint {
+        let mut blocked: Vec<Position> = self.blocked.iter().copied().collect();
+        blocked.sort();
+        let layers = |f: &dyn Fn(&State) -> Vec<f64>| -> Vec<Vec<Vec<Vec<f64>>>> {
+            self.data.iter().map(|row| row.iter().map(|col| col.iter().map(f).collect()).collect()).collect()
+        };
+        Checkpoint {
+            version: CHECKPOINT_VERSION,
+            episodes: self.episodes,
+            rows: self.dimensions.0,
+            columns: self.dimensions.1,
+            start: self.start,
+            finish: self.finish,
+            blocked,
+            rewards: self.rewards,
+            fuel: self.fuel.as_ref().map(|fuel| {
+                let mut chargers: Vec<Position> = fuel.chargers.iter().copied().collect();
+                chargers.sort();
+                FuelBudget {
+                    capacity: fuel.capacity,
+                    chargers,
+                    out_of_fuel_penalty: fuel.penalty
+                }
+            }),
+            action_values: layers(&|state| state.action_values.clone()),
+            visits: self.data
+                .iter()
+                .map(|row| row.iter().map(|col| col.iter().map(|state| state.visits.clone()).collect()).collect())
+                .collect(),
+            rng: RngState {
+                seed: self.rng.get_seed(),
+                stream: self.rng.get_stream(),
+                word_pos: self.rng.get_word_pos()
+            },
+            seed: self.seed
+        }
+    }
+
+    /// Rebuilds a board from `checkpoint`, checking that the stored tables
+    /// match the maze they claim to belong to.
+    pub fn from_checkpoint(checkpoint: Checkpoint) -> Result<Self, CheckpointError> {
+        if checkpoint.version != CHECKPOINT_VERSION {
+            return Err(CheckpointError::UnsupportedVersion(checkpoint.version));
+        }
+        let blocked: HashSet<Position> = checkpoint.blocked.into_iter().collect();
+        let mut board = Board::new(checkpoint.rows, checkpoint.columns, checkpoint.start, checkpoint.finish, &blocked)?
+            .with_rewards(checkpoint.rewards);
+        if let Some(fuel) = checkpoint.fuel {
+            let chargers: HashSet<Position> = fuel.chargers.into_iter().collect();
+            board = board.with_fuel(fuel.capacity, &chargers, fuel.out_of_fuel_penalty)?;
+        }
+        let shape_matches = checkpoint.action_values.len() == board.data.len()
+            && checkpoint.visits.len() == board.data.len()
+            && board.data.iter().zip(checkpoint.action_values.iter().zip(checkpoint.visits.iter())).all(|(row, (values, visits))| {
+                row.len() == values.len() && row.len() == visits.len()
+                    && row.iter().zip(values.iter().zip(visits.iter())).all(|(col, (values, visits))| {
+                        col.len() == values.len() && col.len() == visits.len()
+                            && col.iter().zip(values.iter().zip(visits.iter())).all(|(state, (values, visits))| {
+                                state.actions.len() == values.len() && state.actions.len() == visits.len()
+                            })
+                    })
+            });
+        if !shape_matches {
+            return Err(CheckpointError::Shape);
+        }
+        for (row, (values, visits)) in board.data.iter_mut().zip(checkpoint.action_values.into_iter().zip(checkpoint.visits)) {
+            for (col, (values, visits)) in row.iter_mut().zip(values.into_iter().zip(visits)) {
+                for (state, (values, visits)) in col.iter_mut().zip(values.into_iter().zip(visits)) {
+                    state.action_values = values;
+                    state.visits = visits;
+                }
+            }
+        }
+        board.rng = ChaCha8Rng::from_seed(checkpoint.rng.seed);
+        board.rng.set_stream(checkpoint.rng.stream);
+        board.rng.set_word_pos(checkpoint.rng.word_pos);
+        board.seed = checkpoint.seed;
+        board.episodes = checkpoint.episodes;
+        Ok(board)
     }
 }
 
@@ -220,12 +979,17 @@ impl fmt::Display for Board {
         for (m, row) in self.data.iter().enumerate() {
             for i in 0..4 {
                 write!(f, "|")?;
-                for (n, col) in row.iter().enumerate() {
+                for (n, layers) in row.iter().enumerate() {
+                    // Show the full-tank layer, which is what the agent sees at the start.
+                    let col = &layers[layers.len() - 1];
                     let formatted = action_formatted(col.actions.get(i), col.action_values.get(i));
-                    if (m, n) == self.start {
+                    let cell = Position::new(m, n);
+                    if cell == self.start {
                         write!(f, " {}S|", formatted)?;
-                    } else if (m, n) == self.finish {
+                    } else if cell == self.finish {
                         write!(f, " {}F|", formatted)?;
+                    } else if self.is_charger(cell) {
+                        write!(f, " {}C|", formatted)?;
                     } else {
                         write!(f, " {} |", formatted)?;
                     }
@@ -241,3 +1005,61 @@ impl fmt::Display for Board {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 1 x `length` corridor from the left end to the right end.
+    fn corridor(length: usize) -> Board {
+        Board::new(1, length, Position::new(0, 0), Position::new(0, length - 1), &HashSet::new()).unwrap()
+    }
+
+    #[test]
+    fn running_out_of_fuel_on_the_last_step_is_not_the_limit() {
+        let mut board = corridor(5).with_fuel(2, &HashSet::new(), -100.0).unwrap().with_seed(1);
+        for metrics in board.train(50, 2, 1.0, 0.1, 0.9) {
+            assert_eq!(metrics.steps, 2);
+            assert!(!metrics.reached_finish);
+            assert!(!metrics.hit_limit);
+        }
+        let mut board = corridor(5).with_fuel(2, &HashSet::new(), -100.0).unwrap().with_seed(1);
+        assert!(board.train(10, 1, 1.0, 0.1, 0.9).iter().all(|metrics| metrics.hit_limit));
+    }
+
+    #[test]
+    fn running_out_of_fuel_learns_the_penalty() {
+        let mut board = corridor(3).with_fuel(1, &HashSet::new(), -100.0).unwrap();
+        let metrics = board.train(1, 10, 1.0, 0.5, 0.0);
+        assert_eq!((metrics[0].steps, metrics[0].total_reward), (1, -100.0));
+        assert_eq!(board.state(Position::new(0, 0)).action_values(), &[-50.0]);
+    }
+
+    #[test]
+    fn a_checkpoint_resumes_the_same_run() {
+        let mut board = corridor(6).with_seed(7);
+        board.train(20, 30, 0.9, 0.5, 0.3);
+        let json = serde_json::to_string(&board.checkpoint()).unwrap();
+        let mut resumed = Board::from_checkpoint(serde_json::from_str(&json).unwrap()).unwrap();
+        assert_eq!(resumed.train(20, 30, 0.9, 0.5, 0.3), board.train(20, 30, 0.9, 0.5, 0.3));
+    }
+
+    #[test]
+    fn a_checkpoint_with_the_wrong_shape_is_rejected() {
+        let mut checkpoint = corridor(3).checkpoint();
+        checkpoint.action_values[0][0][0].push(0.0);
+        assert!(matches!(Board::from_checkpoint(checkpoint), Err(CheckpointError::Shape)));
+    }
+
+    #[test]
+    fn a_bad_demonstration_leaves_the_board_untouched() {
+        let mut board = corridor(3);
+        let good = board.rollout(10, 0.0);
+        let mut bad = good.clone();
+        bad[0].to = Position::new(0, 2);
+        let result = board.learn_from_demonstrations(&[good, bad], OfflineUpdate::MonteCarlo, 1.0, 0.5);
+        assert!(matches!(result, Err(DemonstrationError::Mismatch { episode: 1, step: 0 })));
+        assert_eq!(board.episodes(), 0);
+        assert_eq!(board.state(Position::new(0, 0)).action_values(), &[0.0]);
+    }
+}
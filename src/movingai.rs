@@ -0,0 +1,158 @@
+use crate::maze_file::{CellKind, MazeFile, MazeFileError};
+use crate::model::{Position, Rewards};
+use std::fs;
+use std::path::Path;
+
+/// Maps a terrain character to a cell. Ground (`.`, `G`) and swamp (`S`) are
+/// open; out of bounds (`@`, `O`), trees (`T`) and water (`W`) are walls,
+/// since the agent only ever walks on regular terrain.
+fn terrain(symbol: char) -> Option<CellKind> {
+    match symbol {
+        '.' | 'G' | 'S' => Some(CellKind::Open),
+        '@' | 'O' | 'T' | 'W' => Some(CellKind::Wall),
+        _ => None
+    }
+}
+
+fn header_value(line: Option<(usize, &str)>, key: &str) -> Result<usize, MazeFileError> {
+    let (index, line) = line.ok_or(MazeFileError::Empty)?;
+    let mut parts = line.split_whitespace();
+    match (parts.next(), parts.next().and_then(|value| value.parse().ok())) {
+        (Some(found), Some(value)) if found == key => Ok(value),
+        _ => Err(MazeFileError::InvalidHeader { line: index + 1 })
+    }
+}
+
+/// Parses a `.map` file into a grid of cells.
+pub fn parse_map(text: &str) -> Result<Vec<Vec<CellKind>>, MazeFileError> {
+    let mut lines = text.lines().enumerate();
+    match lines.next() {
+        Some((_, line)) if line.trim().starts_with("type") => (),
+        Some((index, _)) => return Err(MazeFileError::InvalidHeader { line: index + 1 }),
+        None => return Err(MazeFileError::Empty)
+    }
+    let height = header_value(lines.next(), "height")?;
+    let width = header_value(lines.next(), "width")?;
+    match lines.next() {
+        Some((_, line)) if line.trim() == "map" => (),
+        Some((index, _)) => return Err(MazeFileError::InvalidHeader { line: index + 1 }),
+        None => return Err(MazeFileError::Empty)
+    }
+
+    let mut cells = Vec::with_capacity(height);
+    for (index, line) in lines.take(height) {
+        let line = line.trim_end();
+        if line.chars().count() != width {
+            return Err(MazeFileError::Ragged { line: index + 1 });
+        }
+        let row = line
+            .chars()
+            .enumerate()
+            .map(|(col, found)| terrain(found).ok_or(MazeFileError::UnknownCell { line: index + 1, column: col + 1, found }))
+            .collect::<Result<Vec<CellKind>, MazeFileError>>()?;
+        cells.push(row);
+    }
+    if cells.len() != height || height == 0 {
+        return Err(MazeFileError::Empty);
+    }
+    Ok(cells)
+}
+
+pub fn load_map(path: impl AsRef<Path>) -> Result<Vec<Vec<CellKind>>, MazeFileError> {
+    parse_map(&fs::read_to_string(path)?)
+}
+
+/// One line of a `.scen` file. `optimal_length` is the benchmark's octile
+/// distance, which allows diagonal moves and so can be shorter than any
+/// four-way path.
+#[derive(Clone, Debug)]
+pub struct Scenario {
+    pub bucket: u32,
+    pub map: String,
+    pub width: usize,
+    pub height: usize,
+    pub start: Position,
+    pub goal: Position,
+    pub optimal_length: f64
+}
+
+impl Scenario {
+    pub fn maze(&self, cells: Vec<Vec<CellKind>>) -> MazeFile {
+        MazeFile {
+            cells,
+            start: self.start,
+            finish: self.goal,
+            rewards: Rewards::default()
+        }
+    }
+}
+
+/// Parses a version 1 `.scen` file. Coordinates in the file are `x` (column)
+/// then `y` (row).
+pub fn parse_scenarios(text: &str) -> Result<Vec<Scenario>, MazeFileError> {
+    let mut scenarios = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with("version") {
+            continue;
+        }
+        let invalid = || MazeFileError::InvalidScenario { line: index + 1 };
+        let fields: Vec<&str> = line.split('\t').collect();
+        let fields = if fields.len() == 9 { fields } else { line.split_whitespace().collect() };
+        if fields.len() != 9 {
+            return Err(invalid());
+        }
+        let number = |i: usize| fields[i].parse::<usize>().map_err(|_| invalid());
+        scenarios.push(Scenario {
+            bucket: fields[0].parse().map_err(|_| invalid())?,
+            map: fields[1].to_string(),
+            width: number(2)?,
+            height: number(3)?,
+            start: Position::new(number(5)?, number(4)?),
+            goal: Position::new(number(7)?, number(6)?),
+            optimal_length: fields[8].parse().map_err(|_| invalid())?
+        });
+    }
+    Ok(scenarios)
+}
+
+pub fn load_scenarios(path: impl AsRef<Path>) -> Result<Vec<Scenario>, MazeFileError> {
+    parse_scenarios(&fs::read_to_string(path)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAP: &str = "type octile\nheight 4\nwidth 5\nmap\n@@@@@\n@.T.@\n@.W.@\n@@@@@\n";
+    const SCEN: &str = "version 1\n0\tsmall.map\t5\t4\t1\t2\t3\t1\t3.41421356\n";
+
+    #[test]
+    fn parses_map_terrain() {
+        let cells = parse_map(MAP).unwrap();
+        assert_eq!((cells.len(), cells[0].len()), (4, 5));
+        assert_eq!(cells[0][0], CellKind::Wall);
+        assert_eq!(cells[1][1], CellKind::Open);
+        assert_eq!(cells[1][2], CellKind::Wall);
+        assert_eq!(cells[2][2], CellKind::Wall);
+    }
+
+    #[test]
+    fn scenario_coordinates_are_x_then_y() {
+        let scenarios = parse_scenarios(SCEN).unwrap();
+        assert_eq!(scenarios.len(), 1);
+        let scenario = &scenarios[0];
+        assert_eq!((scenario.width, scenario.height), (5, 4));
+        // x = 1, y = 2 is row 2, column 1; swapped it would land on the tree at (1, 2).
+        assert_eq!(scenario.start, Position::new(2, 1));
+        assert_eq!(scenario.goal, Position::new(1, 3));
+        let maze = scenario.maze(parse_map(MAP).unwrap());
+        assert_eq!(maze.cells[maze.start.row][maze.start.col], CellKind::Open);
+        assert_eq!(maze.cells[maze.finish.row][maze.finish.col], CellKind::Open);
+    }
+
+    #[test]
+    fn rejects_short_map() {
+        assert!(matches!(parse_map("type octile\nheight 3\nwidth 2\nmap\n..\n..\n"), Err(MazeFileError::Empty)));
+    }
+}